@@ -1,13 +1,37 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use definitions::dto::{definition::DefinitionDto, definition_info::DefinitionSummaryDto};
+use definitions::cache::{ResultCache, DEFAULT_TTL};
+use definitions::dto::{
+    definition::DefinitionDto,
+    definition_info::{DefinitionSummaryDto, RunResultDto},
+    manifest::ProcessorDocumentationDto,
+    plan::PlanDto,
+};
 
-use definitions::definitions::Definitions as DefinitionsImpl;
+use definitions::definitions::{Definitions as DefinitionsImpl, DefinitionsOptions};
 use engine::{
-    definition::input::{BaseDefinitionInput, DefinedDefinitionInput},
-    run, Definitions,
+    asynchronous::{run, AsyncDefinitions},
+    definition::{
+        input::{BaseDefinitionInput, DefinedDefinitionInput},
+        validation::UnresolvedReference,
+    },
+    errors::CharaError,
+    observer::NoopObserver,
+    options::RunOptions,
+    report::RunReport,
 };
-use rocket::serde::json::Json;
+use rocket::{
+    http::{Header, Status},
+    response::status::Custom,
+    serde::json::Json,
+    State,
+};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
 
 use rocket::http::Method;
 use rocket_cors::{AllowedOrigins, CorsOptions};
@@ -15,22 +39,176 @@ use rocket_cors::{AllowedOrigins, CorsOptions};
 #[macro_use]
 extern crate rocket;
 
-#[post("/definitions", data = "<definition>")]
-fn process_definition(definition: Json<DefinitionDto>) -> Json<DefinitionDto> {
-    let definitions: Arc<dyn Definitions> = Arc::new(DefinitionsImpl {});
+#[derive(FromForm)]
+struct RunQuery {
+    max_depth: Option<usize>,
+    max_nodes: Option<usize>,
+    #[field(name = "processor")]
+    processors: Vec<String>,
+    parallelism: Option<usize>,
+    processor_parallelism: Option<usize>,
+    no_cache: Option<bool>,
+    cache_ttl: Option<u64>,
+    timeout: Option<u64>,
+    run: Option<String>,
+}
+
+impl From<&RunQuery> for DefinitionsOptions {
+    fn from(query: &RunQuery) -> Self {
+        DefinitionsOptions {
+            cache: ResultCache {
+                ttl: query.cache_ttl.map_or(DEFAULT_TTL, Duration::from_secs),
+                no_cache: query.no_cache.unwrap_or(false),
+            },
+            timeout: query.timeout.map(Duration::from_secs),
+            cancellation: CancellationToken::new(),
+            ..Default::default()
+        }
+    }
+}
 
-    let definition = run(definition.0.map(), definitions).unwrap();
-    Json(DefinitionDto::from_definition(&definition))
+impl From<RunQuery> for RunOptions {
+    fn from(query: RunQuery) -> Self {
+        RunOptions {
+            max_depth: query.max_depth,
+            max_nodes: query.max_nodes,
+            processors: (!query.processors.is_empty()).then_some(query.processors),
+            parallelism: query.parallelism,
+            processor_parallelism: query.processor_parallelism,
+        }
+    }
+}
+
+const RUN_ID_HEADER: &str = "X-Run-Id";
+
+type ApiError = Custom<String>;
+
+fn api_error(error: CharaError) -> ApiError {
+    let status = match &error {
+        CharaError::IO(error) if error.kind() == std::io::ErrorKind::NotFound => Status::NotFound,
+        CharaError::Json(_)
+        | CharaError::InvalidPath(_)
+        | CharaError::ParsePath
+        | CharaError::InvalidManifest(_, _)
+        | CharaError::InvalidContext(_, _)
+        | CharaError::Placeholder(_, _)
+        | CharaError::UnknownPlugin(_)
+        | CharaError::UnsupportedPlugin(_, _) => Status::UnprocessableEntity,
+        CharaError::Cancelled => Status::Conflict,
+        _ => Status::InternalServerError,
+    };
+    Custom(status, error.to_string())
+}
+
+#[derive(Default)]
+struct Runs(Mutex<HashMap<String, CancellationToken>>);
+
+#[derive(Responder)]
+struct RunResponse {
+    result: Json<RunResultDto>,
+    run_id: Header<'static>,
+}
+
+/// The run id is generated unless given with `run`, so the run can be cancelled while in progress.
+#[post("/definitions?<options..>", data = "<definition>")]
+async fn process_definition(
+    definition: Json<DefinitionDto>,
+    options: RunQuery,
+    runs: &State<Runs>,
+) -> Result<RunResponse, ApiError> {
+    let definitions_options = DefinitionsOptions::from(&options);
+    let cancellation = definitions_options.cancellation.clone();
+    let id = options
+        .run
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    {
+        let mut runs = runs.0.lock().unwrap();
+        if runs.contains_key(&id) {
+            return Err(Custom(Status::Conflict, format!("Run {id} is in progress")));
+        }
+        runs.insert(id.clone(), cancellation.clone());
+    }
+    let definitions: Arc<dyn AsyncDefinitions> =
+        Arc::new(DefinitionsImpl::new(definitions_options));
+    let options = RunOptions::from(options);
+    let definition = definition.0.map();
+
+    let result = run(
+        definition,
+        definitions,
+        &options,
+        Arc::new(NoopObserver),
+        cancellation,
+    )
+    .await;
+    runs.0.lock().unwrap().remove(&id);
+    let (definition, report) = result.map_err(api_error)?;
+    Ok(RunResponse {
+        result: Json(RunResultDto {
+            definition: DefinitionDto::from_definition(&definition),
+            report,
+        }),
+        run_id: Header::new(RUN_ID_HEADER, id),
+    })
+}
+
+#[post("/plans", data = "<definition>")]
+fn plan_definition(definition: Json<DefinitionDto>) -> Result<Json<PlanDto>, ApiError> {
+    PlanDto::from_definition(&definition.0.map())
+        .map(Json)
+        .map_err(api_error)
+}
+
+#[post("/validations", data = "<definition>")]
+fn validate_definition(definition: Json<DefinitionDto>) -> Json<Vec<UnresolvedReference>> {
+    Json(definition.0.map().validate())
+}
+
+#[post("/manifests", data = "<definition>")]
+async fn document_processors(
+    definition: Json<DefinitionDto>,
+) -> Result<Json<Vec<ProcessorDocumentationDto>>, ApiError> {
+    let definition = definition.0.map();
+    // Manifests are read by running the processors, which blocks.
+    let documentation = rocket::tokio::task::spawn_blocking(move || {
+        DefinitionsImpl::default().manifests(&definition)
+    })
+    .await
+    .map_err(|err| Custom(Status::InternalServerError, err.to_string()))?;
+    Ok(Json(documentation))
+}
+
+#[delete("/runs/<id>")]
+fn cancel_run(id: &str, runs: &State<Runs>) -> Status {
+    match runs.0.lock().unwrap().get(id) {
+        Some(cancellation) => {
+            cancellation.cancel();
+            Status::Accepted
+        }
+        None => Status::NotFound,
+    }
 }
 
 #[get("/definitions/<id>")]
-fn get_definition(id: &str) -> Json<DefinitionDto> {
-    Json(DefinitionsImpl::read(&DefinedDefinitionInput::Id(id.to_string())).unwrap())
+fn get_definition(id: &str) -> Result<Json<DefinitionDto>, ApiError> {
+    DefinitionsImpl::read(&DefinedDefinitionInput::Id(id.to_string()))
+        .map(Json)
+        .map_err(api_error)
+}
+
+#[get("/definitions/<id>/report")]
+fn get_report(id: &str) -> Result<Json<RunReport>, ApiError> {
+    DefinitionsImpl::read_report(id)
+        .map(Json)
+        .map_err(api_error)
 }
 
 #[get("/definitions-summaries")]
-fn list_definitions() -> Json<Vec<DefinitionSummaryDto>> {
-    Json(DefinitionsImpl::all_definitions().unwrap())
+fn list_definitions() -> Result<Json<Vec<DefinitionSummaryDto>>, ApiError> {
+    DefinitionsImpl::all_definitions()
+        .map(Json)
+        .map_err(api_error)
 }
 
 #[launch]
@@ -38,15 +216,28 @@ fn rocket() -> _ {
     let cors = CorsOptions::default()
         .allowed_origins(AllowedOrigins::all())
         .allowed_methods(
-            vec![Method::Get, Method::Post, Method::Patch]
+            vec![Method::Get, Method::Post, Method::Patch, Method::Delete]
                 .into_iter()
                 .map(From::from)
                 .collect(),
         )
+        .expose_headers([RUN_ID_HEADER.to_string()].into())
         .allow_credentials(true);
 
-    rocket::build().attach(cors.to_cors().unwrap()).mount(
-        "/api",
-        routes![process_definition, get_definition, list_definitions],
-    )
+    rocket::build()
+        .attach(cors.to_cors().unwrap())
+        .manage(Runs::default())
+        .mount(
+            "/api",
+            routes![
+                process_definition,
+                plan_definition,
+                validate_definition,
+                document_processors,
+                cancel_run,
+                get_definition,
+                get_report,
+                list_definitions
+            ],
+        )
 }
@@ -1,22 +1,265 @@
-use std::sync::Arc;
+use std::{
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use definitions::definitions::Definitions as DefinitionsImpl;
+use clap::{Parser, Subcommand};
+use definitions::{
+    cache::{ResultCache, DEFAULT_TTL},
+    definitions::{Definitions as DefinitionsImpl, DefinitionsOptions},
+    dto::plan::PlanDto,
+};
 use engine::Definitions;
-use engine::{definition::input::BaseDefinitionInput, run};
+use engine::{
+    contexts::DefinitionContextDto, definition::input::BaseDefinitionInput, errors::CharaError,
+    observer::EngineObserver, options::RunOptions, report::ContextReport, run,
+};
 use graph::create_graph;
-fn main() {
-    colog::init();
+use log::{error, info, warn};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Parser, Debug)]
+#[command(name = "chara", version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Process a definition file and save its result
+    Run {
+        file: String,
+        /// Levels of edge definitions processed below the root
+        #[arg(long)]
+        max_depth: Option<usize>,
+        /// Definitions processed during the whole run
+        #[arg(long)]
+        max_nodes: Option<usize>,
+        /// Processor reference allowed to run, can be repeated
+        #[arg(long = "processor")]
+        processors: Vec<String>,
+        /// Worker threads shared by the run
+        #[arg(long)]
+        parallelism: Option<usize>,
+        /// Concurrent executions of a same processor
+        #[arg(long)]
+        processor_parallelism: Option<usize>,
+        /// Run every processor even when a cached output exists
+        #[arg(long)]
+        no_cache: bool,
+        /// Seconds a cached processor output stays valid
+        #[arg(long)]
+        cache_ttl: Option<u64>,
+        /// Seconds a processor may run when it does not set its own timeout
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+    /// Print the processors and foreign definitions a run would execute, without running them
+    Plan { file: String },
+    /// List the references of a definition which could not be resolved
+    Validate { file: String },
+    /// Install every processor the definition declares
+    Install {
+        file: String,
+        /// Seconds an installation may run when it does not set its own timeout
+        #[arg(long)]
+        timeout: Option<u64>,
+    },
+}
+
+/// Logs the number of contexts done out of the contexts scheduled so far.
+#[derive(Default)]
+struct Progress {
+    scheduled: AtomicUsize,
+    done: AtomicUsize,
+}
 
-    let definitions: Arc<dyn Definitions> = Arc::new(DefinitionsImpl {});
+impl Progress {
+    fn done(&self, report: &ContextReport) {
+        let done = self.done.fetch_add(1, Ordering::SeqCst) + 1;
+        info!(
+            "[{done}/{}] {} on metadata {} {:?}",
+            self.scheduled.load(Ordering::SeqCst).max(done),
+            report.processor,
+            report.metadata,
+            report.outcome.status
+        );
+    }
+}
 
-    let definition = DefinitionsImpl::get_from_path(
-        "/home/sbailleul/code/chara/examples/chara.json".to_string(),
-        // "/home/sbailleul/code/chara/chara/cli/chara_results/30ec8abe-533b-4c34-8a9b-2356cc44c2b8.json".to_string(),
+impl EngineObserver for Progress {
+    fn context_scheduled(&self, _definition: &str, _context: &DefinitionContextDto) {
+        self.scheduled.fetch_add(1, Ordering::SeqCst);
+    }
+    fn context_finished(&self, report: &ContextReport) {
+        self.done(report);
+    }
+    fn context_failed(&self, report: &ContextReport) {
+        self.done(report);
+    }
+}
 
-        // "/home/sbailleul/code/chara/chara/cli/chara_results/test.json".to_string(),
-    );
-    if let Ok(definition) = definition {
-        run(definition, definitions);
+fn main() -> ExitCode {
+    colog::init();
+    let args = Args::parse();
+    match args.command {
+        Command::Run {
+            file,
+            max_depth,
+            max_nodes,
+            processors,
+            parallelism,
+            processor_parallelism,
+            no_cache,
+            cache_ttl,
+            timeout,
+        } => {
+            let options = RunOptions {
+                max_depth,
+                max_nodes,
+                processors: (!processors.is_empty()).then_some(processors),
+                parallelism,
+                processor_parallelism,
+            };
+            let cancellation = CancellationToken::new();
+            let handler_cancellation = cancellation.clone();
+            if let Err(err) = ctrlc::set_handler(move || {
+                warn!("Cancel run, running processors are killed");
+                handler_cancellation.cancel();
+            }) {
+                warn!("Ctrl-C handler not installed {err}");
+            }
+            let definitions_options = DefinitionsOptions {
+                cache: ResultCache {
+                    ttl: cache_ttl.map_or(DEFAULT_TTL, Duration::from_secs),
+                    no_cache,
+                },
+                timeout: timeout.map(Duration::from_secs),
+                cancellation: cancellation.clone(),
+                ..Default::default()
+            };
+            let definitions: Arc<dyn Definitions> =
+                Arc::new(DefinitionsImpl::new(definitions_options));
+            match DefinitionsImpl::get_from_path(file).and_then(|definition| {
+                run(
+                    definition,
+                    definitions,
+                    &options,
+                    Arc::new(Progress::default()),
+                    cancellation,
+                )
+            }) {
+                Ok((definition, report)) => {
+                    info!("Definition {} processed", definition.id);
+                    for cycle in &report.cycles {
+                        info!(
+                            "Cycle on metadata {} edge {} to {}",
+                            cycle.metadata, cycle.edge, cycle.definition.id
+                        );
+                    }
+                    for context in &report.contexts {
+                        if let Some(err) = context.outcome.error.as_ref() {
+                            error!(
+                                "Processor {} failed on metadata {} : {err}",
+                                context.processor, context.metadata
+                            );
+                        }
+                    }
+                    for foreign_definition in &report.foreign_definitions {
+                        if let Some(err) = foreign_definition.outcome.error.as_ref() {
+                            error!(
+                                "Foreign definition {} failed to load : {err}",
+                                foreign_definition.key
+                            );
+                        }
+                    }
+                    info!(
+                        "{} contexts run, {} failures",
+                        report.contexts.len(),
+                        report.failures()
+                    );
+                    if report.cancelled {
+                        warn!("Run cancelled, definition {} is partial", definition.id);
+                        ExitCode::FAILURE
+                    } else {
+                        ExitCode::SUCCESS
+                    }
+                }
+                Err(err) => {
+                    error!("{err}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Command::Plan { file } => match DefinitionsImpl::get_from_path(file)
+            .and_then(|definition| PlanDto::from_definition(&definition))
+            .and_then(|plan| serde_json::to_string_pretty(&plan).map_err(CharaError::Json))
+        {
+            Ok(plan) => {
+                println!("{plan}");
+                ExitCode::SUCCESS
+            }
+            Err(err) => {
+                error!("{err}");
+                ExitCode::FAILURE
+            }
+        },
+        Command::Validate { file } => match DefinitionsImpl::get_from_path(file) {
+            Ok(definition) => {
+                let unresolved = definition.validate();
+                for reference in &unresolved {
+                    error!(
+                        "Unresolved reference {} in {} {}",
+                        reference.reference, reference.key, reference.field
+                    );
+                }
+                if unresolved.is_empty() {
+                    info!("Definition {} is valid", definition.id);
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                }
+            }
+            Err(err) => {
+                error!("{err}");
+                ExitCode::FAILURE
+            }
+        },
+        Command::Install { file, timeout } => {
+            let definitions = DefinitionsImpl::new(DefinitionsOptions {
+                timeout: timeout.map(Duration::from_secs),
+                ..Default::default()
+            });
+            match DefinitionsImpl::get_from_path(file) {
+                Ok(definition) => {
+                    let mut failures = 0;
+                    for (processor_reference, result) in definitions.install_processors(&definition)
+                    {
+                        match result {
+                            Ok(()) => info!("Processor {processor_reference} installed"),
+                            Err(err) => {
+                                failures += 1;
+                                error!("Processor {processor_reference} failed to install : {err}");
+                            }
+                        }
+                    }
+                    if failures == 0 {
+                        ExitCode::SUCCESS
+                    } else {
+                        ExitCode::FAILURE
+                    }
+                }
+                Err(err) => {
+                    error!("{err}");
+                    ExitCode::FAILURE
+                }
+            }
+        }
     }
 }
 
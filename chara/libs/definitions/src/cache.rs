@@ -0,0 +1,80 @@
+use std::{fs, time::Duration};
+
+use engine::errors::CharaError;
+use log::info;
+use sha2::{Digest, Sha256};
+
+use crate::definitions::create_path;
+
+pub const DEFAULT_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Processor outputs stored in `chara_cache` under a hash of everything the processor receives.
+#[derive(Debug, Clone)]
+pub struct ResultCache {
+    pub ttl: Duration,
+    /// Ignores the stored outputs, fresh outputs are still stored for the next runs.
+    pub no_cache: bool,
+}
+
+impl Default for ResultCache {
+    fn default() -> Self {
+        Self {
+            ttl: DEFAULT_TTL,
+            no_cache: false,
+        }
+    }
+}
+
+impl ResultCache {
+    pub fn key<'a>(
+        context: &str,
+        program: &str,
+        current_directory: Option<&str>,
+        arguments: &[String],
+        environment_keys: impl Iterator<Item = &'a String>,
+    ) -> String {
+        let mut environment_keys = environment_keys.collect::<Vec<&String>>();
+        environment_keys.sort();
+        let mut hasher = Sha256::new();
+        for section in [
+            vec![context],
+            vec![program, current_directory.unwrap_or_default()],
+            arguments.iter().map(|argument| argument.as_str()).collect(),
+            environment_keys.iter().map(|key| key.as_str()).collect(),
+        ] {
+            for part in section {
+                hasher.update(part.len().to_le_bytes());
+                hasher.update(part.as_bytes());
+            }
+            hasher.update([0xff]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Path of the stored output when it is younger than the ttl.
+    pub fn get(&self, key: &str) -> Result<Option<String>, CharaError> {
+        if self.no_cache {
+            return Ok(None);
+        }
+        let path = create_path("chara_cache", Some(key))?;
+        let Ok(metadata) = fs::metadata(&path) else {
+            return Ok(None);
+        };
+        let age = metadata
+            .modified()
+            .map_err(CharaError::IO)?
+            .elapsed()
+            .unwrap_or(Duration::ZERO);
+        if age > self.ttl {
+            info!("Cached output {path} expired");
+            return Ok(None);
+        }
+        info!("Use cached output {path}");
+        fs::read_to_string(path).map(Some).map_err(CharaError::IO)
+    }
+
+    pub fn store(&self, key: &str, output: &str) -> Result<(), CharaError> {
+        let path = create_path("chara_cache", Some(key))?;
+        fs::write(path, output).map_err(CharaError::IO)
+    }
+}
@@ -1,12 +1,31 @@
-use std::{collections::HashMap, fs::canonicalize, process::Command};
+use std::{
+    collections::HashMap,
+    env,
+    fs::canonicalize,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Child, Command, Output, Stdio},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
 
 use common::ThreadError;
 use engine::{
     cli::{Arguments, Environment},
     definition::install::Install,
-    errors::CharaError, processor::{DefinedProcessorOverride, Processor},
+    errors::CharaError,
+    processor::{DefinedProcessorOverride, Processor, RetryPolicy},
 };
-use log::info;
+use log::{info, warn};
+use tokio::io::AsyncWriteExt;
+
+use crate::{plugin::is_builtin, secret::Secrets, template::Placeholders};
+use tokio_util::sync::CancellationToken;
+
+pub const MASK: &str = "********";
+/// Prefix of the paths resolved from the working directory rather than from the declaring definition.
+pub const CWD: &str = "${cwd}";
+
 pub trait Inputs {
     fn arguments(&self) -> Vec<Arguments>;
     fn environments(&self) -> Vec<Environment>;
@@ -29,78 +48,423 @@ pub trait Inputs {
 pub trait Cli: Inputs {
     fn program(&self) -> Result<String, CharaError>;
     fn current_directory(&self) -> Result<Option<String>, CharaError>;
-    fn command(&self, additional_arguments: Option<Vec<String>>) -> Result<Command, CharaError> {
-        self.program().and_then(|program| {
-            let mut cmd = Command::new(&program);
-            info!("Run program {program}");
-            if let Some(current_directory) = self.current_directory()?.as_ref() {
-                info!("Current directory {current_directory}");
-                let current_directory = canonicalize(current_directory).map_err(CharaError::IO)?;
-                cmd.current_dir(current_directory);
-            } else {
-                info!("No current directory")
+    /// Location of the definition declaring the command, its relative paths are resolved from its directory.
+    fn location(&self) -> Result<Option<String>, CharaError>;
+    fn resolved_current_directory(
+        &self,
+        placeholders: &Placeholders,
+    ) -> Result<Option<PathBuf>, CharaError> {
+        self.current_directory()?
+            .map(|current_directory| {
+                let current_directory = placeholders.render(&current_directory)?;
+                resolve_path(&current_directory, self.location()?.as_deref())
+            })
+            .transpose()
+    }
+    /// Program path resolved from the current directory, or from the declaring definition without one.
+    /// A bare program name is looked up in the `PATH`, a builtin program is kept as is.
+    fn resolved_program(&self, current_directory: Option<&Path>) -> Result<PathBuf, CharaError> {
+        let program = self.program()?;
+        if is_builtin(&program)
+            || (!program.starts_with(CWD) && Path::new(&program).components().count() == 1)
+        {
+            return Ok(PathBuf::from(program));
+        }
+        match current_directory {
+            Some(current_directory) if Path::new(&program).is_relative() => {
+                Ok(current_directory.join(program))
             }
-            let additional_arguments = additional_arguments.unwrap_or(vec![]);
-            cmd.args(&additional_arguments);
-            let arguments = [self.flatten_arguments(), additional_arguments].concat();
-            let environments = self.flatten_environments();
-            info!("Arguments {}", arguments.join(" ").escape_default());
-            info!(
-                "Environments {}",
-                environments
-                    .iter()
-                    .map(|(k, v)| format!("{k}={v}"))
-                    .collect::<Vec<String>>()
-                    .join("\n")
-            );
-            cmd.args(self.flatten_arguments())
-                .envs(self.flatten_environments());
-            Ok(cmd)
-        })
+            _ => resolve_path(&program, self.location()?.as_deref()),
+        }
     }
-    fn output_stdout(
+    /// Builtin programs run in-process, they are never spawned.
+    fn spawned_program(&self, current_directory: Option<&Path>) -> Result<PathBuf, CharaError> {
+        let program = self.program()?;
+        if is_builtin(&program) {
+            return Err(CharaError::UnsupportedPlugin(
+                program,
+                "be run as a program".to_string(),
+            ));
+        }
+        self.resolved_program(current_directory)
+    }
+    /// Runs the program alone, without the arguments nor environments of the processor.
+    fn bare_command(&self, arguments: &[String]) -> Result<Command, CharaError> {
+        let current_directory = self.resolved_current_directory(&Placeholders::default())?;
+        let mut command = Command::new(self.spawned_program(current_directory.as_deref())?);
+        if let Some(current_directory) = current_directory {
+            command.current_dir(current_directory);
+        }
+        command.args(arguments);
+        Ok(command)
+    }
+    fn command(
         &self,
         additional_arguments: Option<Vec<String>>,
-    ) -> Result<String, CharaError> {
-        self.command(additional_arguments).and_then(|mut cmd| {
-            info!("Run command");
-            cmd.output().map_err(CharaError::IO).and_then(|output| {
-                if output.status.success() {
-                    String::from_utf8(output.stdout)
-                        .map_err(CharaError::ParseUtf8)
-                        .inspect(|stdout| {
-                            info!("Stdout {stdout}");
-                        })
+        secrets: &Secrets,
+    ) -> Result<Command, CharaError> {
+        self.command_with(
+            additional_arguments,
+            &Placeholders::without_context(secrets),
+        )
+    }
+    /// Builds the command with its secrets revealed and its placeholders rendered.
+    fn command_with(
+        &self,
+        additional_arguments: Option<Vec<String>>,
+        placeholders: &Placeholders,
+    ) -> Result<Command, CharaError> {
+        let current_directory = self
+            .resolved_current_directory(placeholders)?
+            .map(|current_directory| canonicalize(current_directory).map_err(CharaError::IO))
+            .transpose()?;
+        self.spawned_program(current_directory.as_deref())
+            .and_then(|program| {
+                let mut cmd = Command::new(&program);
+                info!("Run program {}", program.display());
+                if let Some(current_directory) = current_directory {
+                    info!("Current directory {}", current_directory.display());
+                    cmd.current_dir(current_directory);
                 } else {
-                    String::from_utf8(output.stderr)
-                        .map_err(CharaError::ParseUtf8)
-                        .and_then(|stderr| {
-                            Err(CharaError::Process(format!(
-                                "Processor execution failed [Error : {stderr}]"
-                            )))
-                        })
+                    info!("No current directory")
                 }
+                let additional_arguments = additional_arguments.unwrap_or(vec![]);
+                cmd.args(&additional_arguments);
+                let location = self.location()?;
+                let arguments = self
+                    .flatten_arguments()
+                    .iter()
+                    .map(|argument| placeholders.resolve(argument, location.as_deref()))
+                    .collect::<Result<Vec<String>, CharaError>>()?;
+                let environments = self
+                    .flatten_environments()
+                    .into_iter()
+                    .map(|(key, value)| {
+                        placeholders
+                            .resolve(&value, location.as_deref())
+                            .map(|value| (key, value))
+                    })
+                    .collect::<Result<HashMap<String, String>, CharaError>>()?;
+                let secrets = placeholders.secrets();
+                info!(
+                    "Arguments {}",
+                    secrets
+                        .mask(&[arguments.clone(), additional_arguments].concat().join(" "))
+                        .escape_default()
+                );
+                info!(
+                    "Environments {}",
+                    environments
+                        .iter()
+                        .map(|(k, v)| format!("{k}={}", secrets.mask(v)))
+                        .collect::<Vec<String>>()
+                        .join("\n")
+                );
+                cmd.args(arguments).envs(environments);
+                Ok(cmd)
             })
+    }
+    fn timeout(&self) -> Option<Duration>;
+    fn retry(&self) -> Option<RetryPolicy>;
+    fn output_stdout(
+        &self,
+        additional_arguments: Option<Vec<String>>,
+        default_timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        self.command(additional_arguments, secrets).and_then(|cmd| {
+            command_stdout(
+                cmd,
+                None,
+                self.timeout().or(default_timeout),
+                self.retry().as_ref(),
+                secrets,
+                cancellation,
+            )
         })
     }
 }
 
+/// Resolves a relative path from the directory of the definition location, or from the working directory when prefixed by `${cwd}`.
+pub fn resolve_path(path: &str, location: Option<&str>) -> Result<PathBuf, CharaError> {
+    if let Some(path) = path.strip_prefix(CWD) {
+        return env::current_dir()
+            .map(|current_directory| current_directory.join(path.trim_start_matches(['/', '\\'])))
+            .map_err(CharaError::IO);
+    }
+    let path = Path::new(path);
+    Ok(
+        match location.and_then(|location| Path::new(location).parent()) {
+            Some(directory) if path.is_relative() => directory.join(path),
+            _ => path.to_path_buf(),
+        },
+    )
+}
+
+/// Runs the command until it succeeds, the retry policy gives up or the run is cancelled, the input is written to its stdin.
+pub fn command_stdout(
+    mut cmd: Command,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+    retry: Option<&RetryPolicy>,
+    secrets: &Secrets,
+    cancellation: &CancellationToken,
+) -> Result<String, CharaError> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    retried(retry, &program, || {
+        run_command(&mut cmd, &program, input, timeout, secrets, cancellation)
+    })
+}
+
+/// Runs `run` again while the retry policy accepts its failure.
+pub(crate) fn retried(
+    retry: Option<&RetryPolicy>,
+    program: &str,
+    mut run: impl FnMut() -> Result<String, CharaError>,
+) -> Result<String, CharaError> {
+    let mut attempt = 1;
+    loop {
+        info!("Run {program} attempt {attempt}");
+        let result = run();
+        match retry_delay(retry, attempt, program, &result) {
+            Some(delay) => thread::sleep(delay),
+            None => return result,
+        }
+        attempt += 1;
+    }
+}
+
+/// Runs the command on the tokio process driver until it succeeds or the retry policy gives up, the child is killed when the future is dropped.
+pub async fn command_stdout_async(
+    cmd: Command,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+    retry: Option<&RetryPolicy>,
+    secrets: &Secrets,
+    cancellation: &CancellationToken,
+) -> Result<String, CharaError> {
+    let program = cmd.get_program().to_string_lossy().to_string();
+    let mut cmd = tokio::process::Command::from(cmd);
+    cmd.kill_on_drop(true);
+    let mut attempt = 1;
+    loop {
+        info!("Run {program} attempt {attempt}");
+        let result =
+            run_command_async(&mut cmd, &program, input, timeout, secrets, cancellation).await;
+        match retry_delay(retry, attempt, &program, &result) {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return result,
+        }
+        attempt += 1;
+    }
+}
+
+/// Delay before the next attempt, `None` once the result is final.
+pub(crate) fn retry_delay(
+    retry: Option<&RetryPolicy>,
+    attempt: u32,
+    program: &str,
+    result: &Result<String, CharaError>,
+) -> Option<Duration> {
+    let Err(err) = result else {
+        return None;
+    };
+    match retry {
+        Some(retry) if attempt < retry.attempts && retry.is_retryable(err) => {
+            let delay = retry.delay(attempt);
+            warn!(
+                "Attempt {attempt}/{} of {program} failed, retry in {delay:?} : {err}",
+                retry.attempts
+            );
+            Some(delay)
+        }
+        _ => {
+            warn!("Attempt {attempt} of {program} failed : {err}");
+            None
+        }
+    }
+}
+
+/// Runs the command once, its process group is killed when it outlives the timeout or when the run is cancelled.
+fn run_command(
+    cmd: &mut Command,
+    program: &str,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+    secrets: &Secrets,
+    cancellation: &CancellationToken,
+) -> Result<String, CharaError> {
+    if cancellation.is_cancelled() {
+        return Err(CharaError::Cancelled);
+    }
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(cmd, 0);
+    let mut child = cmd
+        .stdin(stdin(input))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(CharaError::IO)?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        let input = input.to_string();
+        // Written aside so a child filling its stdout before reading stdin does not block the run.
+        thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
+    let stdout_reader = child.stdout.take().map(read_pipe);
+    let stderr_reader = child.stderr.take().map(read_pipe);
+    let deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(CharaError::IO)? {
+            break status;
+        }
+        if cancellation.is_cancelled() {
+            kill(&mut child);
+            let _ = child.wait();
+            return Err(CharaError::Cancelled);
+        }
+        if let Some((deadline, timeout)) = deadline {
+            if Instant::now() >= deadline {
+                kill(&mut child);
+                let _ = child.wait();
+                return Err(CharaError::Timeout(program.to_string(), timeout));
+            }
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    stdout(
+        Output {
+            status,
+            stdout: join_pipe(stdout_reader)?,
+            stderr: join_pipe(stderr_reader)?,
+        },
+        secrets,
+    )
+}
+
+async fn run_command_async(
+    cmd: &mut tokio::process::Command,
+    program: &str,
+    input: Option<&str>,
+    timeout: Option<Duration>,
+    secrets: &Secrets,
+    cancellation: &CancellationToken,
+) -> Result<String, CharaError> {
+    if cancellation.is_cancelled() {
+        return Err(CharaError::Cancelled);
+    }
+    #[cfg(unix)]
+    cmd.process_group(0);
+    let mut child = cmd
+        .stdin(stdin(input))
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(CharaError::IO)?;
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        let input = input.to_string();
+        tokio::spawn(async move { stdin.write_all(input.as_bytes()).await });
+    }
+    let pid = child.id();
+    let deadline = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    let result = tokio::select! {
+        output = child.wait_with_output() => return output.map_err(CharaError::IO).and_then(|output| stdout(output, secrets)),
+        _ = cancellation.cancelled() => Err(CharaError::Cancelled),
+        _ = deadline => Err(CharaError::Timeout(program.to_string(), timeout.unwrap_or_default())),
+    };
+    if let Some(pid) = pid {
+        kill_group(pid);
+    }
+    result
+}
+
+fn stdin(input: Option<&str>) -> Stdio {
+    match input {
+        Some(_) => Stdio::piped(),
+        None => Stdio::null(),
+    }
+}
+
+fn read_pipe(mut pipe: impl Read + Send + 'static) -> JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        pipe.read_to_end(&mut buffer).map(|_| buffer)
+    })
+}
+
+fn join_pipe(reader: Option<JoinHandle<std::io::Result<Vec<u8>>>>) -> Result<Vec<u8>, CharaError> {
+    reader.map_or(Ok(vec![]), |reader| {
+        reader
+            .join()
+            .map_err(|_| CharaError::Thread(ThreadError::Join))?
+            .map_err(CharaError::IO)
+    })
+}
+
+pub(crate) fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    kill_group(child.id());
+    #[cfg(not(unix))]
+    let _ = child.kill();
+}
+
+#[cfg(unix)]
+pub(crate) fn kill_group(pid: u32) {
+    // The child leads its own process group, so every process it spawned is killed with it.
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+pub(crate) fn kill_group(_pid: u32) {}
+
+fn stdout(output: Output, secrets: &Secrets) -> Result<String, CharaError> {
+    if output.status.success() {
+        if !output.stderr.is_empty() {
+            info!(
+                "Stderr {}",
+                secrets.mask(&String::from_utf8_lossy(&output.stderr))
+            );
+        }
+        String::from_utf8(output.stdout)
+            .map_err(CharaError::ParseUtf8)
+            .inspect(|stdout| {
+                info!("Stdout {}", secrets.mask(stdout));
+            })
+    } else {
+        String::from_utf8(output.stderr)
+            .map_err(CharaError::ParseUtf8)
+            .and_then(|stderr| {
+                Err(CharaError::ProcessExit(
+                    output.status.code(),
+                    secrets.mask(&stderr),
+                ))
+            })
+    }
+}
+
 impl Inputs for Install {
     fn arguments(&self) -> Vec<Arguments> {
         self.arguments
-        .clone()
-        .iter()
-        .map(|arg| arg.to_ref_or_value())
-        .flatten()
-        .collect()
+            .clone()
+            .iter()
+            .map(|arg| arg.to_ref_or_value())
+            .flatten()
+            .collect()
     }
     fn environments(&self) -> Vec<Environment> {
         self.environments
-        .clone()
-        .iter()
-        .map(|env| env.to_ref_or_value())
-        .flatten()
-        .collect()
+            .clone()
+            .iter()
+            .map(|env| env.to_ref_or_value())
+            .flatten()
+            .collect()
     }
 }
 impl Cli for Install {
@@ -110,6 +474,15 @@ impl Cli for Install {
     fn current_directory(&self) -> Result<Option<String>, CharaError> {
         Ok(self.current_directory.clone())
     }
+    fn location(&self) -> Result<Option<String>, CharaError> {
+        Ok(self.location.clone())
+    }
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+    fn retry(&self) -> Option<RetryPolicy> {
+        None
+    }
 }
 
 impl Inputs for Processor {
@@ -138,6 +511,18 @@ impl Cli for Processor {
     fn current_directory(&self) -> Result<Option<String>, CharaError> {
         Ok(self.current_directory.clone())
     }
+
+    fn location(&self) -> Result<Option<String>, CharaError> {
+        Ok(self.location.clone())
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn retry(&self) -> Option<RetryPolicy> {
+        self.retry.clone()
+    }
 }
 
 impl Inputs for DefinedProcessorOverride {
@@ -187,4 +572,28 @@ impl Cli for DefinedProcessorOverride {
             .map(|processor| processor.current_directory.clone())
             .or(Err(CharaError::Thread(ThreadError::Poison)))
     }
+
+    fn location(&self) -> Result<Option<String>, CharaError> {
+        self.processor
+            .value
+            .read()
+            .map(|processor| processor.location.clone())
+            .or(Err(CharaError::Thread(ThreadError::Poison)))
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.processor
+            .value
+            .read()
+            .ok()
+            .and_then(|processor| processor.timeout)
+    }
+
+    fn retry(&self) -> Option<RetryPolicy> {
+        self.processor
+            .value
+            .read()
+            .ok()
+            .and_then(|processor| processor.retry.clone())
+    }
 }
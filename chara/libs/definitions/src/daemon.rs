@@ -0,0 +1,435 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::ThreadError;
+use engine::{contexts::DefinitionContextDto, errors::CharaError, processor::RetryPolicy};
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    cli::{kill_group, retried, retry_delay},
+    dto::rpc::{EnrichParamsDto, RpcRequestDto, RpcResponseDto, JSON_RPC_VERSION},
+    secret::Secrets,
+};
+
+/// Time a daemon has to exit once asked to shut down, it is killed afterwards.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval at which a blocking request checks whether the run was cancelled.
+const CANCELLATION_POLL: Duration = Duration::from_millis(100);
+
+/// Channel of the blocking or the asynchronous request awaiting a response.
+enum Responder {
+    Blocking(mpsc::SyncSender<RpcResponseDto>),
+    Async(oneshot::Sender<RpcResponseDto>),
+}
+
+impl Responder {
+    fn send(self, response: RpcResponseDto) {
+        match self {
+            Responder::Blocking(sender) => {
+                let _ = sender.send(response);
+            }
+            Responder::Async(sender) => {
+                let _ = sender.send(response);
+            }
+        }
+    }
+}
+
+type Pending = Arc<Mutex<HashMap<u64, Responder>>>;
+
+/// Processor started once, answering on stdout every request written on its stdin.
+struct Daemon {
+    program: String,
+    pid: u32,
+    child: Mutex<Child>,
+    stdin: Mutex<Option<ChildStdin>>,
+    pending: Pending,
+}
+
+impl Daemon {
+    fn spawn(command: &mut Command, secrets: &Secrets) -> Result<Self, CharaError> {
+        let program = command.get_program().to_string_lossy().to_string();
+        info!("Start daemon {program}");
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(command, 0);
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(CharaError::IO)?;
+        let stdin = child.stdin.take().ok_or(CharaError::Process(format!(
+            "Daemon {program} has no stdin"
+        )))?;
+        let pending = Pending::default();
+        if let Some(stdout) = child.stdout.take() {
+            let pending = pending.clone();
+            let program = program.clone();
+            thread::spawn(move || dispatch_responses(&program, stdout, pending));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            let program = program.clone();
+            let secrets = secrets.clone();
+            thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    info!("{program} {}", secrets.mask(&line));
+                }
+            });
+        }
+        Ok(Daemon {
+            program,
+            pid: child.id(),
+            child: Mutex::new(child),
+            stdin: Mutex::new(Some(stdin)),
+            pending,
+        })
+    }
+
+    fn send<T: Serialize>(&self, request: &RpcRequestDto<T>) -> Result<(), CharaError> {
+        let mut line = serde_json::to_vec(request).map_err(CharaError::Json)?;
+        line.push(b'\n');
+        let mut stdin = self
+            .stdin
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        let stdin = stdin.as_mut().ok_or_else(|| exited(&self.program))?;
+        stdin
+            .write_all(&line)
+            .and_then(|_| stdin.flush())
+            .map_err(CharaError::IO)
+    }
+
+    fn forget(&self, id: u64) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&id);
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.child
+            .lock()
+            .is_ok_and(|mut child| matches!(child.try_wait(), Ok(None)))
+    }
+
+    fn shutdown(self: Arc<Self>) {
+        info!("Shut daemon {} down", self.program);
+        let shutdown = RpcRequestDto::<()> {
+            jsonrpc: JSON_RPC_VERSION,
+            id: None,
+            method: "shutdown",
+            params: None,
+        };
+        if let Err(err) = self.send(&shutdown) {
+            warn!("Daemon {} not asked to shut down {err}", self.program);
+        }
+        if let Ok(mut stdin) = self.stdin.lock() {
+            stdin.take();
+        }
+        let (exited, exit) = mpsc::channel();
+        let waiter = {
+            let daemon = self.clone();
+            thread::spawn(move || {
+                if let Ok(mut child) = daemon.child.lock() {
+                    let _ = child.wait();
+                }
+                let _ = exited.send(());
+            })
+        };
+        if exit.recv_timeout(SHUTDOWN_TIMEOUT).is_err() {
+            warn!("Kill daemon {} after {SHUTDOWN_TIMEOUT:?}", self.program);
+            kill_group(self.pid);
+        }
+        let _ = waiter.join();
+    }
+}
+
+/// Hands each response to the request with the same id, the pending requests fail once the daemon closes its stdout.
+fn dispatch_responses(program: &str, stdout: impl Read, pending: Pending) {
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        let response = match serde_json::from_str::<RpcResponseDto>(&line) {
+            Ok(response) => response,
+            Err(err) => {
+                warn!("Daemon {program} wrote an invalid response {err}");
+                continue;
+            }
+        };
+        let responder = response.id.and_then(|id| pending.lock().ok()?.remove(&id));
+        match responder {
+            Some(responder) => responder.send(response),
+            None => info!(
+                "Daemon {program} answered request {:?} no longer awaited",
+                response.id
+            ),
+        }
+    }
+    info!("Daemon {program} closed its stdout");
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+}
+
+struct Request<R> {
+    id: u64,
+    daemon: Arc<Daemon>,
+    receiver: R,
+}
+
+/// Processor daemons of a run keyed by their command, each one is started by its first request.
+#[derive(Default)]
+pub(crate) struct Daemons {
+    next_id: AtomicU64,
+    daemons: Mutex<HashMap<String, Arc<Daemon>>>,
+}
+
+impl Daemons {
+    fn daemon(&self, command: &mut Command, secrets: &Secrets) -> Result<Arc<Daemon>, CharaError> {
+        let key = command_key(command);
+        let mut daemons = self
+            .daemons
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        match daemons.get(&key) {
+            Some(daemon) if daemon.is_running() => return Ok(daemon.clone()),
+            Some(daemon) => warn!("Daemon {} exited, it is restarted", daemon.program),
+            None => {}
+        }
+        let daemon = Arc::new(Daemon::spawn(command, secrets)?);
+        daemons.insert(key, daemon.clone());
+        Ok(daemon)
+    }
+
+    fn request<R>(
+        &self,
+        command: &mut Command,
+        context: &DefinitionContextDto,
+        secrets: &Secrets,
+        (responder, receiver): (Responder, R),
+    ) -> Result<Request<R>, CharaError> {
+        let daemon = self.daemon(command, secrets)?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        daemon
+            .pending
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+            .insert(id, responder);
+        let sent = daemon.send(&RpcRequestDto {
+            jsonrpc: JSON_RPC_VERSION,
+            id: Some(id),
+            method: "enrich",
+            params: Some(EnrichParamsDto { context }),
+        });
+        if let Err(err) = sent {
+            daemon.forget(id);
+            return Err(err);
+        }
+        Ok(Request {
+            id,
+            daemon,
+            receiver,
+        })
+    }
+
+    /// A failed request is sent again according to the retry policy, to a restarted daemon if it exited.
+    pub fn enrich(
+        &self,
+        mut command: Command,
+        context: &DefinitionContextDto,
+        timeout: Option<Duration>,
+        retry: Option<&RetryPolicy>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let program = command.get_program().to_string_lossy().to_string();
+        retried(retry, &program, || {
+            self.attempt(&mut command, context, timeout, secrets, cancellation)
+        })
+    }
+
+    pub async fn enrich_async(
+        &self,
+        mut command: Command,
+        context: &DefinitionContextDto,
+        timeout: Option<Duration>,
+        retry: Option<&RetryPolicy>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let program = command.get_program().to_string_lossy().to_string();
+        let mut attempt = 1;
+        loop {
+            info!("Run {program} attempt {attempt}");
+            let result = self
+                .attempt_async(&mut command, context, timeout, secrets, cancellation)
+                .await;
+            match retry_delay(retry, attempt, &program, &result) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return result,
+            }
+            attempt += 1;
+        }
+    }
+
+    /// A request timing out or cancelled is forgotten, the daemon keeps serving the others.
+    fn attempt(
+        &self,
+        command: &mut Command,
+        context: &DefinitionContextDto,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let request = self.request(
+            command,
+            context,
+            secrets,
+            (Responder::Blocking(sender), receiver),
+        )?;
+        let program = &request.daemon.program;
+        let deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+        let result = loop {
+            if cancellation.is_cancelled() {
+                break Err(CharaError::Cancelled);
+            }
+            let wait = match deadline {
+                Some((deadline, timeout)) => {
+                    match deadline.checked_duration_since(Instant::now()) {
+                        Some(remaining) => remaining.min(CANCELLATION_POLL),
+                        None => break Err(CharaError::Timeout(program.clone(), timeout)),
+                    }
+                }
+                None => CANCELLATION_POLL,
+            };
+            match request.receiver.recv_timeout(wait) {
+                Ok(response) => return response_result(program, response, secrets),
+                Err(RecvTimeoutError::Disconnected) => return Err(exited(program)),
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+        };
+        request.daemon.forget(request.id);
+        result
+    }
+
+    async fn attempt_async(
+        &self,
+        command: &mut Command,
+        context: &DefinitionContextDto,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let (sender, receiver) = oneshot::channel();
+        let request = self.request(
+            command,
+            context,
+            secrets,
+            (Responder::Async(sender), receiver),
+        )?;
+        let program = &request.daemon.program;
+        let deadline = async {
+            match timeout {
+                Some(timeout) => tokio::time::sleep(timeout).await,
+                None => std::future::pending().await,
+            }
+        };
+        let result = tokio::select! {
+            response = request.receiver => return response
+                .map_err(|_| exited(program))
+                .and_then(|response| response_result(program, response, secrets)),
+            _ = cancellation.cancelled() => Err(CharaError::Cancelled),
+            _ = deadline => Err(CharaError::Timeout(program.clone(), timeout.unwrap_or_default())),
+        };
+        request.daemon.forget(request.id);
+        result
+    }
+
+    /// Asks every daemon to shut down, the ones still running after [`SHUTDOWN_TIMEOUT`] are killed.
+    pub fn shutdown(&self) {
+        let daemons = self
+            .daemons
+            .lock()
+            .map(|mut daemons| daemons.drain().map(|(_, daemon)| daemon).collect())
+            .unwrap_or(vec![]);
+        for daemon in daemons {
+            daemon.shutdown();
+        }
+    }
+
+    pub async fn shutdown_async(self: Arc<Self>) {
+        if let Err(err) = tokio::task::spawn_blocking(move || self.shutdown()).await {
+            warn!("Daemons not shut down {err}");
+        }
+    }
+}
+
+impl Drop for Daemons {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Contexts share a daemon only when their processor runs the exact same command.
+fn command_key(command: &Command) -> String {
+    let mut environments = command
+        .get_envs()
+        .map(|(key, value)| {
+            format!(
+                "{}={}",
+                key.to_string_lossy(),
+                value
+                    .map(|value| value.to_string_lossy())
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<String>>();
+    environments.sort();
+    [
+        vec![command.get_program().to_string_lossy().to_string()],
+        vec![command
+            .get_current_dir()
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or_default()],
+        command
+            .get_args()
+            .map(|argument| argument.to_string_lossy().to_string())
+            .collect(),
+        environments,
+    ]
+    .concat()
+    .join("\0")
+}
+
+fn response_result(
+    program: &str,
+    response: RpcResponseDto,
+    secrets: &Secrets,
+) -> Result<String, CharaError> {
+    match response.error {
+        Some(error) => Err(CharaError::Process(format!(
+            "Daemon {program} failed with code {} : {}",
+            error.code,
+            secrets.mask(&error.message)
+        ))),
+        None => serde_json::to_string(&response.result.unwrap_or(Value::Object(Map::new())))
+            .map_err(CharaError::Json),
+    }
+}
+
+fn exited(program: &str) -> CharaError {
+    CharaError::ProcessExit(None, format!("Daemon {program} exited before answering"))
+}
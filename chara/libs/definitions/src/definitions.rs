@@ -3,40 +3,118 @@ use std::{
     fs::{self, canonicalize, read_dir, DirEntry, File},
     io::BufReader,
     path::PathBuf,
+    process::Command,
+    sync::Arc,
+    time::Duration,
 };
 
+use async_trait::async_trait;
+
 use common::{thread::Readonly, ThreadError};
 use engine::{
-    contexts::ProcessorContext,
+    asynchronous::AsyncDefinitions,
+    contexts::{DefinitionContextDto, ProcessorContext},
     definition::definition::Definition,
     definition::input::{BaseDefinitionInput, DefinedDefinitionInput},
     errors::CharaError,
-    processor::{Enrichment, ProcessorResult},
+    processor::{
+        Enrichment, Processor, ProcessorResult, Protocol, RetryPolicy, STDIO_PROTOCOL_VERSION,
+    },
+    report::RunReport,
     Definitions as ForeignDefinitions,
 };
-use log::info;
+use log::{info, warn};
 use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    cli::Cli,
+    cache::ResultCache,
+    cli::{command_stdout, command_stdout_async, Cli, Inputs},
+    daemon::Daemons,
     dto::{
-        definition::{DefinitionDto, ProcessorResultDto},
+        definition::{DefinitionDto, ProcessorRequestDto, ProcessorResultDto},
         definition_info::DefinitionSummaryDto,
+        manifest::ProcessorDocumentationDto,
     },
+    install::{InstallCommands, Installs},
+    manifest::Manifests,
+    output,
+    plugin::{ProcessorPlugin, ProcessorPlugins},
+    secret::Secrets,
+    template::Placeholders,
+    wasm::{is_wasm, WasmCommand, WasmRuntime},
 };
 
-pub struct Definitions {}
+const REPORT_EXTENSION: &str = ".report.json";
+
+#[derive(Debug, Clone, Default)]
+pub struct DefinitionsOptions {
+    pub cache: ResultCache,
+    /// Time a processor or an installation may run when it does not set its own timeout.
+    pub timeout: Option<Duration>,
+    /// Kills the running processes once cancelled, share it with the engine run to cancel both.
+    pub cancellation: CancellationToken,
+    /// Run in-process by the processors whose program is `builtin:{name}`.
+    pub plugins: ProcessorPlugins,
+}
+
+#[derive(Default)]
+pub struct Definitions {
+    options: DefinitionsOptions,
+    installs: Arc<Installs>,
+    manifests: Arc<Manifests>,
+    daemons: Arc<Daemons>,
+    wasm: Arc<WasmRuntime>,
+    secrets: Secrets,
+}
+
+/// Commands run to enrich a context, with their timeouts and the key of their output in the result cache.
+struct ProcessorCommands {
+    /// Referenced processor, without the arguments and environments layered by the context.
+    processor: Processor,
+    install: Option<InstallCommands>,
+    command: Command,
+    /// Written to the processor stdin, only for the stdio protocol.
+    input: Option<String>,
+    protocol: Protocol,
+    /// Run by the embedded runtime in place of the command.
+    wasm: Option<WasmCommand>,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    cache_key: String,
+}
 pub struct ReadOutput<T> {
     output: T,
     location: Option<String>,
 }
 impl Definitions {
+    pub fn new(options: DefinitionsOptions) -> Self {
+        Self {
+            options,
+            installs: Arc::default(),
+            manifests: Arc::default(),
+            daemons: Arc::default(),
+            wasm: Arc::default(),
+            secrets: Secrets::default(),
+        }
+    }
     pub fn read(input: &DefinedDefinitionInput) -> Result<DefinitionDto, CharaError> {
-        Definitions::read_output::<DefinitionDto>(input).map(|def| def.output)
+        Definitions::read_output::<DefinitionDto>(
+            input,
+            None,
+            &Secrets::default(),
+            &CancellationToken::new(),
+        )
+        .map(|def| def.output)
     }
     pub fn get_from_path(path: String) -> Result<Definition, CharaError> {
-        Definitions::read_output::<DefinitionDto>(&BaseDefinitionInput::File(path.clone()))
-            .map(|read_output| DefinitionDto::map_overwrite_location(read_output.output, path))
+        Definitions::read_output::<DefinitionDto>(
+            &BaseDefinitionInput::File(path.clone()),
+            None,
+            &Secrets::default(),
+            &CancellationToken::new(),
+        )
+        .map(|read_output| DefinitionDto::map_overwrite_location(read_output.output, path))
     }
     pub fn get_from_definition(definition: DefinitionDto) -> Result<Definition, CharaError> {
         Ok(definition.map())
@@ -62,6 +140,9 @@ impl Definitions {
 
     fn read_output<T: for<'a> Deserialize<'a>>(
         input: &DefinedDefinitionInput,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
     ) -> Result<ReadOutput<T>, CharaError> {
         let mut location = None;
         dbg!(input);
@@ -75,7 +156,7 @@ impl Definitions {
             BaseDefinitionInput::Processor(processor) => {
                 info!("Run definition processor");
                 processor
-                    .output_stdout(None)
+                    .output_stdout(None, timeout, secrets, cancellation)
                     .and_then(|stdout| serde_json::from_str(&stdout).map_err(CharaError::Json))
             }
             BaseDefinitionInput::Value(value) => {
@@ -85,9 +166,316 @@ impl Definitions {
         .map(|output| ReadOutput { output, location })
     }
 
+    async fn read_output_async<T: for<'a> Deserialize<'a>>(
+        input: &DefinedDefinitionInput,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<ReadOutput<T>, CharaError> {
+        match input {
+            BaseDefinitionInput::Processor(processor) => {
+                info!("Run definition processor");
+                let command = processor.command(None, secrets)?;
+                command_stdout_async(
+                    command,
+                    None,
+                    processor.timeout().or(timeout),
+                    processor.retry().as_ref(),
+                    secrets,
+                    cancellation,
+                )
+                .await
+                .and_then(|stdout| serde_json::from_str(&stdout).map_err(CharaError::Json))
+                .map(|output| ReadOutput {
+                    output,
+                    location: None,
+                })
+            }
+            input => Definitions::read_output(input, timeout, secrets, cancellation),
+        }
+    }
+
+    /// Plugin run in place of the processor program of the context.
+    fn plugin(
+        &self,
+        context: &ProcessorContext,
+    ) -> Result<Option<Arc<dyn ProcessorPlugin>>, CharaError> {
+        let processor = context
+            .processor
+            .processor
+            .value
+            .read()
+            .or(Err(CharaError::Thread(ThreadError::Poison)))?;
+        let plugin = self.options.plugins.get(&processor.program)?;
+        if plugin.is_some() {
+            if processor.install.is_some() {
+                return Err(CharaError::UnsupportedPlugin(
+                    processor.program.clone(),
+                    "be installed".to_string(),
+                ));
+            }
+            if processor.manifest.is_some() {
+                return Err(CharaError::UnsupportedPlugin(
+                    processor.program.clone(),
+                    "declare a manifest".to_string(),
+                ));
+            }
+        }
+        Ok(plugin)
+    }
+
+    /// Installation and processor commands of a context, built before running them so no lock is held meanwhile.
+    fn processor_commands(
+        context: &ProcessorContext,
+        output_path: &str,
+        default_timeout: Option<Duration>,
+        secrets: &Secrets,
+    ) -> Result<ProcessorCommands, CharaError> {
+        let processor = &context.processor;
+        let value = processor
+            .processor
+            .value
+            .read()
+            .map(|value| value.clone())
+            .or(Err(CharaError::Thread(ThreadError::Poison)))?;
+        let install = InstallCommands::new(&value, default_timeout, secrets)?;
+        let placeholders = Placeholders::new(&context.definition, secrets);
+        let wasm = is_wasm(&value.program);
+        let protocol = match value.protocol {
+            Protocol::Arguments if wasm => Protocol::Stdio {
+                version: STDIO_PROTOCOL_VERSION,
+            },
+            Protocol::JsonRpc if wasm => {
+                return Err(CharaError::Process(format!(
+                    "Wasm processor {} speaks the stdio protocol only",
+                    value.program
+                )))
+            }
+            protocol => protocol,
+        };
+        let (context, input) = match protocol {
+            Protocol::Arguments | Protocol::JsonRpc => (
+                serde_json::to_string(&context.definition).map_err(CharaError::Json)?,
+                None,
+            ),
+            Protocol::Stdio { version } if version == STDIO_PROTOCOL_VERSION => {
+                let input = serde_json::to_string(&ProcessorRequestDto {
+                    version,
+                    context: &context.definition,
+                })
+                .map_err(CharaError::Json)?;
+                (input.clone(), Some(input))
+            }
+            Protocol::Stdio { version } => {
+                return Err(CharaError::Process(format!(
+                "Unsupported stdio protocol version {version}, expected {STDIO_PROTOCOL_VERSION}"
+            )))
+            }
+        };
+        let (command, additional_arguments) = match protocol {
+            Protocol::Arguments => {
+                let additional_arguments = vec![
+                    "--context".to_string(),
+                    context.clone(),
+                    "--output".to_string(),
+                    output_path.to_string(),
+                ];
+                let count = additional_arguments.len();
+                (
+                    processor.command_with(Some(additional_arguments), &placeholders)?,
+                    count,
+                )
+            }
+            Protocol::Stdio { .. } | Protocol::JsonRpc => {
+                (processor.command_with(None, &placeholders)?, 0)
+            }
+        };
+        let cache_key = ResultCache::key(
+            &context,
+            &command.get_program().to_string_lossy(),
+            command
+                .get_current_dir()
+                .map(|current_directory| current_directory.to_string_lossy())
+                .as_deref(),
+            &command
+                .get_args()
+                .skip(additional_arguments)
+                .map(|argument| argument.to_string_lossy().to_string())
+                .collect::<Vec<String>>(),
+            processor.flatten_environments().keys(),
+        );
+        let timeout = processor.timeout().or(default_timeout);
+        let wasm = wasm.then(|| {
+            WasmCommand::new(
+                &command,
+                input.clone().unwrap_or_default(),
+                value.location.as_deref(),
+                value.network,
+                timeout,
+                processor.retry(),
+            )
+        });
+        Ok(ProcessorCommands {
+            processor: value,
+            install,
+            command,
+            input,
+            protocol,
+            wasm,
+            timeout,
+            retry: processor.retry(),
+            cache_key,
+        })
+    }
+
+    /// Installs every processor declared by the definition, a processor whose check succeeds is left as is.
+    pub fn install_processors(
+        &self,
+        definition: &Definition,
+    ) -> Vec<(String, Result<(), CharaError>)> {
+        let mut names: Vec<&String> = definition.processors.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let processor_reference = format!("#/{name}");
+                let install = definition.processors[name]
+                    .read()
+                    .or(Err(CharaError::Thread(ThreadError::Poison)))
+                    .and_then(|processor| {
+                        InstallCommands::new(&processor, self.options.timeout, &self.secrets)
+                    })
+                    .transpose()?
+                    .and_then(|install| {
+                        self.installs.install(
+                            &processor_reference,
+                            install,
+                            &self.secrets,
+                            &self.options.cancellation,
+                        )
+                    });
+                Some((processor_reference, install))
+            })
+            .collect()
+    }
+
+    /// Manifests of the processors declared by the definition, the ones without manifest are documented without it.
+    pub fn manifests(&self, definition: &Definition) -> Vec<ProcessorDocumentationDto> {
+        let mut names: Vec<&String> = definition.processors.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let processor_reference = format!("#/{name}");
+                let manifest = definition.processors[name]
+                    .read()
+                    .or(Err(CharaError::Thread(ThreadError::Poison)))
+                    .and_then(|processor| {
+                        self.manifests.get(
+                            &processor_reference,
+                            &processor,
+                            processor.timeout.or(self.options.timeout),
+                            &self.secrets,
+                            &self.options.cancellation,
+                        )
+                    });
+                match manifest {
+                    Ok(manifest) => ProcessorDocumentationDto {
+                        processor_reference,
+                        manifest: manifest.map(|manifest| manifest.dto.clone()),
+                        error: None,
+                    },
+                    Err(err) => ProcessorDocumentationDto {
+                        processor_reference,
+                        manifest: None,
+                        error: Some(err.to_string()),
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn processor_output(
+        protocol: Protocol,
+        output_path: &str,
+        stdout: String,
+    ) -> Result<String, CharaError> {
+        match protocol {
+            Protocol::Arguments => {
+                let output = fs::read_to_string(output_path).map_err(CharaError::IO);
+                if let Err(err) = fs::remove_file(output_path) {
+                    warn!("Processor output {output_path} not removed {err}");
+                }
+                output
+            }
+            Protocol::Stdio { .. } | Protocol::JsonRpc => Ok(stdout),
+        }
+    }
+
+    fn write_cache(&self, cache_key: &str, output: &str) {
+        if let Err(err) = self.options.cache.store(cache_key, output) {
+            warn!("Processor output not cached {err}");
+        }
+    }
+
+    /// Reads and validates the output of the processor of the context.
+    fn processor_result(
+        output: &str,
+        context: &DefinitionContextDto,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        let result = output::parse_result(output, context)?;
+        Definitions::map_result(result, context.location.clone(), context, parent)
+    }
+
+    /// Result of a plugin, checked as the output of a processor program.
+    fn plugin_result(
+        result: ProcessorResultDto,
+        context: &DefinitionContextDto,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        output::check_permissions(&result, context)?;
+        Definitions::map_result(result, context.location.clone(), context, parent)
+    }
+
+    fn map_result(
+        result: ProcessorResultDto,
+        location: Option<String>,
+        context: &DefinitionContextDto,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        let definition = result
+            .definition
+            .map(|def| def.map_with_location(location, Some(parent.clone())));
+        if let Some(definition) = definition.as_ref() {
+            let parent = parent
+                .read()
+                .or(Err(CharaError::Thread(ThreadError::Poison)))?;
+            for unresolved in output::unresolved_references(definition, &parent) {
+                warn!(
+                    "Output of processor {} {unresolved}",
+                    context.processor_reference
+                );
+            }
+        }
+        Ok(ProcessorResult {
+            definition,
+            enrichment: result.enrichment.map(|enrichment| Enrichment {
+                edge: enrichment.edge,
+                metadata: enrichment.metadata,
+            }),
+        })
+    }
+
     fn result_path(id: &str) -> Result<String, CharaError> {
         create_path("chara_results", Some(id))
     }
+    fn report_path(id: &str) -> Result<String, CharaError> {
+        create_path("chara_results", Some(&format!("{id}{REPORT_EXTENSION}")))
+    }
+    pub fn read_report(id: &str) -> Result<RunReport, CharaError> {
+        Definitions::report_path(id).and_then(|path| Definitions::read_from_file(&path, &mut None))
+    }
     pub fn all_definitions() -> Result<Vec<DefinitionSummaryDto>, CharaError> {
         read_dir(get_directory("chara_results")?)
             .map_err(CharaError::IO)
@@ -95,10 +483,16 @@ impl Definitions {
                 res.map(|e| {
                     e.map_err(CharaError::IO)
                         .map(|e| {
-                            e.path().to_str().map(|path| {
-                                dbg!(path);
-                                Self::read_from_file::<DefinitionSummaryDto>(&path.to_string(), &mut None)
-                            })
+                            e.path()
+                                .to_str()
+                                .filter(|path| !path.ends_with(REPORT_EXTENSION))
+                                .map(|path| {
+                                    dbg!(path);
+                                    Self::read_from_file::<DefinitionSummaryDto>(
+                                        &path.to_string(),
+                                        &mut None,
+                                    )
+                                })
                         })
                         .transpose()
                 })
@@ -110,7 +504,13 @@ impl Definitions {
 }
 impl ForeignDefinitions for Definitions {
     fn get(&self, input: &DefinedDefinitionInput) -> Result<Definition, CharaError> {
-        Definitions::read_output::<DefinitionDto>(input).map(|read_output| {
+        Definitions::read_output::<DefinitionDto>(
+            input,
+            self.options.timeout,
+            &self.secrets,
+            &self.options.cancellation,
+        )
+        .map(|read_output| {
             DefinitionDto::map_with_location(read_output.output, read_output.location, None)
         })
     }
@@ -125,50 +525,191 @@ impl ForeignDefinitions for Definitions {
         .map_err(CharaError::Json)?;
         Ok(())
     }
+    fn save_report(&self, definition: &Definition, report: &RunReport) -> Result<(), CharaError> {
+        let path = Definitions::report_path(definition.id.as_str())?;
+        info!("Save report at {path}");
+        serde_json::to_writer(File::create(path).map_err(CharaError::IO)?, report)
+            .map_err(CharaError::Json)?;
+        Ok(())
+    }
     fn enrich(
         &self,
         context: &ProcessorContext,
         parent: Readonly<Definition>,
     ) -> Result<ProcessorResult, CharaError> {
-        context
-            .processor
-            .processor
-            .value
-            .read()
-            .or(Err(CharaError::Thread(ThreadError::Poison)))
-            .and_then(|processor| {
-                if let Some(install) = &processor.install {
-                    info!("Run installation");
-                    let install_output = install.output_stdout(None)?;
-                    info!("Installation done : {install_output}");
+        if let Some(plugin) = self.plugin(context)? {
+            let result = plugin.process(&context.definition)?;
+            return Definitions::plugin_result(result, &context.definition, parent);
+        }
+        let path = create_path("processor_outputs", None)?;
+        let commands =
+            Definitions::processor_commands(context, &path, self.options.timeout, &self.secrets)?;
+        let output = match self.options.cache.get(&commands.cache_key)? {
+            Some(output) => output,
+            None => {
+                if let Some(install) = commands.install {
+                    self.installs.install(
+                        &context.definition.processor_reference,
+                        install,
+                        &self.secrets,
+                        &self.options.cancellation,
+                    )?;
+                }
+                if let Some(manifest) = self.manifests.get(
+                    &context.definition.processor_reference,
+                    &commands.processor,
+                    commands.timeout,
+                    &self.secrets,
+                    &self.options.cancellation,
+                )? {
+                    manifest.validate(&context.definition)?;
                 }
-                let context =
-                    serde_json::to_string(&context.definition).map_err(CharaError::Json)?;
+                let stdout = match (commands.protocol, commands.wasm) {
+                    (Protocol::JsonRpc, _) => self.daemons.enrich(
+                        commands.command,
+                        &context.definition,
+                        commands.timeout,
+                        commands.retry.as_ref(),
+                        &self.secrets,
+                        &self.options.cancellation,
+                    )?,
+                    (_, Some(wasm)) => {
+                        self.wasm
+                            .stdout(wasm, &self.secrets, &self.options.cancellation)?
+                    }
+                    _ => command_stdout(
+                        commands.command,
+                        commands.input.as_deref(),
+                        commands.timeout,
+                        commands.retry.as_ref(),
+                        &self.secrets,
+                        &self.options.cancellation,
+                    )?,
+                };
+                let output = Definitions::processor_output(commands.protocol, &path, stdout)?;
+                self.write_cache(&commands.cache_key, &output);
+                output
+            }
+        };
+        Definitions::processor_result(&output, &context.definition, parent)
+    }
 
-                let path = create_path("processor_outputs", None)?;
-                processor
-                    .output_stdout(Some(vec![
-                        "--context".to_string(),
-                        context,
-                        "--output".to_string(),
-                        path.clone(),
-                    ]))
-                    .and_then(|_output| {
-                        Definitions::read_output::<ProcessorResultDto>(&BaseDefinitionInput::File(
-                            path,
-                        ))
-                    })
-                    .map(|result| ProcessorResult {
-                        definition: result
-                            .output
-                            .definition
-                            .map(|def| def.map_with_location(result.location, Some(parent))),
-                        enrichment: result.output.enrichment.map(|enrichment| Enrichment {
-                            edge: enrichment.edge,
-                            metadata: enrichment.metadata,
-                        }),
-                    })
-            })
+    fn finish(&self) {
+        self.daemons.shutdown();
+    }
+}
+
+#[async_trait]
+impl AsyncDefinitions for Definitions {
+    async fn get(&self, input: &DefinedDefinitionInput) -> Result<Definition, CharaError> {
+        Definitions::read_output_async::<DefinitionDto>(
+            input,
+            self.options.timeout,
+            &self.secrets,
+            &self.options.cancellation,
+        )
+        .await
+        .map(|read_output| {
+            DefinitionDto::map_with_location(read_output.output, read_output.location, None)
+        })
+    }
+
+    async fn save(&self, definition: &Definition) -> Result<(), CharaError> {
+        ForeignDefinitions::save(self, definition)
+    }
+
+    async fn save_report(
+        &self,
+        definition: &Definition,
+        report: &RunReport,
+    ) -> Result<(), CharaError> {
+        ForeignDefinitions::save_report(self, definition, report)
+    }
+
+    async fn enrich(
+        &self,
+        context: &ProcessorContext,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        if let Some(plugin) = self.plugin(context)? {
+            let definition = context.definition.clone();
+            let result = tokio::task::spawn_blocking(move || plugin.process(&definition))
+                .await
+                .map_err(|err| CharaError::Process(err.to_string()))??;
+            return Definitions::plugin_result(result, &context.definition, parent);
+        }
+        let path = create_path("processor_outputs", None)?;
+        let commands =
+            Definitions::processor_commands(context, &path, self.options.timeout, &self.secrets)?;
+        let output = match self.options.cache.get(&commands.cache_key)? {
+            Some(output) => output,
+            None => {
+                if let Some(install) = commands.install {
+                    self.installs
+                        .clone()
+                        .install_async(
+                            &context.definition.processor_reference,
+                            install,
+                            &self.secrets,
+                            &self.options.cancellation,
+                        )
+                        .await?;
+                }
+                if let Some(manifest) = self
+                    .manifests
+                    .clone()
+                    .get_async(
+                        &context.definition.processor_reference,
+                        &commands.processor,
+                        commands.timeout,
+                        &self.secrets,
+                        &self.options.cancellation,
+                    )
+                    .await?
+                {
+                    manifest.validate(&context.definition)?;
+                }
+                let stdout = match (commands.protocol, commands.wasm) {
+                    (Protocol::JsonRpc, _) => {
+                        self.daemons
+                            .enrich_async(
+                                commands.command,
+                                &context.definition,
+                                commands.timeout,
+                                commands.retry.as_ref(),
+                                &self.secrets,
+                                &self.options.cancellation,
+                            )
+                            .await?
+                    }
+                    (_, Some(wasm)) => {
+                        self.wasm
+                            .clone()
+                            .stdout_async(wasm, &self.secrets, &self.options.cancellation)
+                            .await?
+                    }
+                    _ => {
+                        command_stdout_async(
+                            commands.command,
+                            commands.input.as_deref(),
+                            commands.timeout,
+                            commands.retry.as_ref(),
+                            &self.secrets,
+                            &self.options.cancellation,
+                        )
+                        .await?
+                    }
+                };
+                let output = Definitions::processor_output(commands.protocol, &path, stdout)?;
+                self.write_cache(&commands.cache_key, &output);
+                output
+            }
+        };
+        Definitions::processor_result(&output, &context.definition, parent)
+    }
+
+    async fn finish(&self) {
+        self.daemons.clone().shutdown_async().await;
     }
 }
 
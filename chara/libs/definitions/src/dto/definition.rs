@@ -119,6 +119,10 @@ pub struct InstallDto {
     pub environments: Vec<EnvironmentDto>,
     #[serde(rename(deserialize = "currentDirectory", serialize = "currentDirectory"))]
     pub current_directory: Option<String>,
+    /// Seconds the installation may run before being killed.
+    pub timeout: Option<u64>,
+    /// Arguments given to the processor program, the installation is skipped when it succeeds.
+    pub check: Option<Vec<String>>,
 }
 
 pub type EnvironmentDto = ReferenceOrObjectDto<HashMap<String, String>>;
@@ -144,6 +148,46 @@ pub struct ProcessorDto {
     pub install: Option<InstallDto>,
     #[serde(rename(deserialize = "currentDirectory", serialize = "currentDirectory"))]
     pub current_directory: Option<String>,
+    /// Seconds the processor may run before being killed.
+    pub timeout: Option<u64>,
+    pub retry: Option<RetryDto>,
+    pub protocol: Option<ProtocolDto>,
+    /// `true` to run the processor with `--manifest`, or the path of its manifest file.
+    pub manifest: Option<ManifestSourceDto>,
+    /// `true` to let a wasm processor use the network.
+    pub network: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ManifestSourceDto {
+    Command(bool),
+    File(String),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(tag = "name", rename_all = "lowercase")]
+pub enum ProtocolDto {
+    Arguments,
+    Stdio { version: u32 },
+    JsonRpc,
+}
+
+/// Written to the stdin of a processor speaking the stdio protocol.
+#[derive(Debug, Serialize)]
+pub struct ProcessorRequestDto<'a> {
+    pub version: u32,
+    pub context: &'a DefinitionContextDto,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct RetryDto {
+    pub attempts: u32,
+    /// Milliseconds before the second attempt, doubled on each following attempt.
+    #[serde(default)]
+    pub backoff: u64,
+    #[serde(default, rename(deserialize = "exitCodes", serialize = "exitCodes"))]
+    pub exit_codes: Vec<i32>,
 }
 #[derive(Debug, Deserialize, Serialize, Hash, Clone)]
 pub struct ProcessorOverrideDto {
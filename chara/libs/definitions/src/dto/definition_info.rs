@@ -1,7 +1,16 @@
+use engine::report::RunReport;
 use serde::{Deserialize, Serialize};
 
+use super::definition::DefinitionDto;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DefinitionSummaryDto {
     id: String,
     name: String,
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunResultDto {
+    pub definition: DefinitionDto,
+    pub report: RunReport,
+}
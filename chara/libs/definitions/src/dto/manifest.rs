@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::definition::ProtocolDto;
+
+/// What a processor expects and writes, printed with `--manifest` or read from its manifest file.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ProcessorManifestDto {
+    pub name: String,
+    pub version: String,
+    pub protocol: Option<ProtocolDto>,
+    /// JSON Schema of the metadata value given to the processor.
+    pub metadata: Option<Value>,
+    /// JSON Schema of the edge value given to the processor.
+    pub edge: Option<Value>,
+    #[serde(default)]
+    pub writes: Vec<WriteTargetDto>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum WriteTargetDto {
+    #[serde(rename = "enrichment.edge")]
+    EnrichmentEdge,
+    #[serde(rename = "enrichment.metadata")]
+    EnrichmentMetadata,
+    #[serde(rename = "definition")]
+    Definition,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ProcessorDocumentationDto {
+    pub processor_reference: String,
+    pub manifest: Option<ProcessorManifestDto>,
+    pub error: Option<String>,
+}
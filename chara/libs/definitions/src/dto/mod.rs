@@ -1,2 +1,5 @@
 pub mod definition;
-pub mod definition_info;
\ No newline at end of file
+pub mod definition_info;
+pub mod manifest;
+pub mod plan;
+pub mod rpc;
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use engine::contexts::DefinitionContextDto;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What a run of a definition would execute, built without running any program.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlanDto {
+    pub processors: Vec<PlannedProcessorDto>,
+    pub foreign_definitions: Vec<PlannedForeignDefinitionDto>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedCommandDto {
+    pub processor_reference: String,
+    pub program: String,
+    #[serde(rename(deserialize = "currentDirectory", serialize = "currentDirectory"))]
+    pub current_directory: Option<String>,
+    pub arguments: Vec<String>,
+    pub environments: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedProcessorDto {
+    #[serde(flatten)]
+    pub command: PlannedCommandDto,
+    pub context: DefinitionContextDto,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PlannedForeignDefinitionDto {
+    pub key: String,
+    pub input: PlannedInputDto,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlannedInputDto {
+    File(String),
+    Id(String),
+    Text(String),
+    Value(Value),
+    Processor(PlannedCommandDto),
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::definition::DefinitionContextDto;
+
+pub const JSON_RPC_VERSION: &str = "2.0";
+
+/// Line written to the stdin of a processor daemon, notifications have no id.
+#[derive(Debug, Serialize)]
+pub struct RpcRequestDto<'a, T> {
+    pub jsonrpc: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub method: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<T>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnrichParamsDto<'a> {
+    pub context: &'a DefinitionContextDto,
+}
+
+/// Line read from the stdout of a processor daemon, answering the request with the same id.
+#[derive(Debug, Deserialize)]
+pub struct RpcResponseDto {
+    pub id: Option<u64>,
+    pub result: Option<Value>,
+    pub error: Option<RpcErrorDto>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RpcErrorDto {
+    pub code: i64,
+    pub message: String,
+}
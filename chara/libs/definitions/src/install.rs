@@ -0,0 +1,153 @@
+use std::{
+    collections::HashMap,
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::ThreadError;
+use engine::{
+    errors::CharaError,
+    processor::{processor_identity, Processor},
+};
+use log::info;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    cli::{command_stdout, Cli},
+    plugin::is_builtin,
+    secret::Secrets,
+};
+
+/// Installation of a processor, skipped when its check succeeds.
+pub(crate) struct InstallCommands {
+    check: Option<Command>,
+    command: Command,
+    timeout: Option<Duration>,
+    location: Option<String>,
+}
+
+impl InstallCommands {
+    pub fn new(
+        processor: &Processor,
+        default_timeout: Option<Duration>,
+        secrets: &Secrets,
+    ) -> Result<Option<Self>, CharaError> {
+        processor
+            .install
+            .as_ref()
+            .map(|install| {
+                if is_builtin(&processor.program) {
+                    return Err(CharaError::UnsupportedPlugin(
+                        processor.program.clone(),
+                        "be installed".to_string(),
+                    ));
+                }
+                Ok(InstallCommands {
+                    check: install
+                        .check
+                        .as_ref()
+                        .map(|arguments| processor.bare_command(arguments))
+                        .transpose()?,
+                    command: install.command(None, secrets)?,
+                    timeout: install.timeout().or(default_timeout),
+                    location: processor.location.clone(),
+                })
+            })
+            .transpose()
+    }
+}
+
+type InstallOutcome = Arc<Mutex<Option<Result<(), String>>>>;
+
+/// Installations done by a run, keyed by processor reference so each processor is installed once.
+#[derive(Default)]
+pub(crate) struct Installs {
+    outcomes: Mutex<HashMap<String, InstallOutcome>>,
+}
+
+impl Installs {
+    fn outcome(
+        &self,
+        processor_reference: &str,
+        location: Option<&str>,
+    ) -> Result<InstallOutcome, CharaError> {
+        self.outcomes
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))
+            .map(|mut outcomes| {
+                outcomes
+                    .entry(processor_identity(processor_reference, location))
+                    .or_default()
+                    .clone()
+            })
+    }
+
+    /// Blocks the contexts of the processor while its installation runs, must not be called from an asynchronous task.
+    pub fn install(
+        &self,
+        processor_reference: &str,
+        commands: InstallCommands,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<(), CharaError> {
+        let outcome = self.outcome(processor_reference, commands.location.as_deref())?;
+        let mut outcome = outcome
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        if let Some(previous) = outcome.as_ref() {
+            return previous_outcome(processor_reference, previous);
+        }
+        let installed = match commands.check {
+            Some(check) => {
+                command_stdout(check, None, commands.timeout, None, secrets, cancellation).is_ok()
+            }
+            None => false,
+        };
+        let result = if installed {
+            info!("Processor {processor_reference} already installed");
+            Ok(())
+        } else {
+            info!("Install processor {processor_reference}");
+            command_stdout(
+                commands.command,
+                None,
+                commands.timeout,
+                None,
+                secrets,
+                cancellation,
+            )
+            .map(|output| info!("Installation done : {output}"))
+        };
+        *outcome = Some(result.as_ref().map(|_| ()).map_err(|err| err.to_string()));
+        result
+    }
+
+    pub async fn install_async(
+        self: Arc<Self>,
+        processor_reference: &str,
+        commands: InstallCommands,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<(), CharaError> {
+        let processor_reference = processor_reference.to_string();
+        let secrets = secrets.clone();
+        let cancellation = cancellation.clone();
+        tokio::task::spawn_blocking(move || {
+            self.install(&processor_reference, commands, &secrets, &cancellation)
+        })
+        .await
+        .map_err(|err| CharaError::Process(err.to_string()))?
+    }
+}
+
+fn previous_outcome(
+    processor_reference: &str,
+    previous: &Result<(), String>,
+) -> Result<(), CharaError> {
+    previous.clone().map_err(|err| {
+        CharaError::Process(format!(
+            "Installation of {processor_reference} failed {err}"
+        ))
+    })
+}
@@ -1,5 +1,14 @@
 
+pub mod cache;
 mod cli;
+mod daemon;
+mod install;
+mod manifest;
+mod output;
+pub mod plugin;
+pub mod secret;
+pub mod template;
+mod wasm;
 pub mod dto;
 pub mod definitions;
 mod mappers;
@@ -0,0 +1,234 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::BufReader,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use common::ThreadError;
+use engine::{
+    contexts::DefinitionContextDto,
+    errors::CharaError,
+    processor::{processor_identity, ManifestSource, Processor},
+};
+use jsonschema::Validator;
+use log::info;
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    cli::{command_stdout, resolve_path, Cli},
+    dto::manifest::ProcessorManifestDto,
+    secret::Secrets,
+};
+
+/// Manifest of a processor with its schemas compiled.
+pub(crate) struct ProcessorManifest {
+    pub dto: ProcessorManifestDto,
+    metadata: Option<Validator>,
+    edge: Option<Validator>,
+}
+
+impl ProcessorManifest {
+    fn new(processor_reference: &str, dto: ProcessorManifestDto) -> Result<Self, CharaError> {
+        let compile = |schema: &Option<Value>| {
+            schema
+                .as_ref()
+                .map(|schema| {
+                    jsonschema::validator_for(schema).map_err(|err| {
+                        CharaError::InvalidManifest(
+                            processor_reference.to_string(),
+                            err.to_string(),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(ProcessorManifest {
+            metadata: compile(&dto.metadata)?,
+            edge: compile(&dto.edge)?,
+            dto,
+        })
+    }
+
+    /// Checks the metadata and edge values of the context against the schemas of the manifest.
+    pub fn validate(&self, context: &DefinitionContextDto) -> Result<(), CharaError> {
+        let metadata = Value::Object(context.metadata.value.clone());
+        let edge = context
+            .edge
+            .as_ref()
+            .map(|edge| Value::Object(edge.value.clone()));
+        let errors = [
+            ("metadata", self.metadata.as_ref(), Some(&metadata)),
+            ("edge", self.edge.as_ref(), edge.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, validator, value)| Some((name, validator?, value?)))
+        .flat_map(|(name, validator, value)| {
+            validator
+                .iter_errors(value)
+                .map(move |err| format!("{name}{} {err}", err.instance_path))
+        })
+        .collect::<Vec<String>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(CharaError::InvalidContext(
+                context.processor_reference.clone(),
+                errors.join(", "),
+            ))
+        }
+    }
+}
+
+type ManifestOutcome = Arc<Mutex<Option<Result<Option<Arc<ProcessorManifest>>, String>>>>;
+
+/// Manifests read by a run, keyed by processor reference so each manifest is read once.
+#[derive(Default)]
+pub(crate) struct Manifests {
+    outcomes: Mutex<HashMap<String, ManifestOutcome>>,
+}
+
+impl Manifests {
+    fn outcome(
+        &self,
+        processor_reference: &str,
+        location: Option<&str>,
+    ) -> Result<ManifestOutcome, CharaError> {
+        self.outcomes
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))
+            .map(|mut outcomes| {
+                outcomes
+                    .entry(processor_identity(processor_reference, location))
+                    .or_default()
+                    .clone()
+            })
+    }
+
+    /// Manifest of the processor, `None` when it does not declare one. Must not be called from an asynchronous task.
+    pub fn get(
+        &self,
+        processor_reference: &str,
+        processor: &Processor,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<Arc<ProcessorManifest>>, CharaError> {
+        let outcome = self.outcome(processor_reference, processor.location.as_deref())?;
+        let mut outcome = outcome
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        if let Some(previous) = outcome.as_ref() {
+            return previous_outcome(processor_reference, previous);
+        }
+        let result = match &processor.manifest {
+            Some(ManifestSource::Command) => {
+                info!("Read manifest of processor {processor_reference}");
+                processor
+                    .bare_command(&["--manifest".to_string()])
+                    .and_then(|command| {
+                        command_stdout(command, None, timeout, None, secrets, cancellation)
+                    })
+                    .and_then(|stdout| parse_manifest(processor_reference, &stdout))
+            }
+            source => read_manifest(
+                processor_reference,
+                source.as_ref(),
+                processor.location.as_deref(),
+            ),
+        };
+        store(processor_reference, &mut outcome, result)
+    }
+
+    pub async fn get_async(
+        self: Arc<Self>,
+        processor_reference: &str,
+        processor: &Processor,
+        timeout: Option<Duration>,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<Option<Arc<ProcessorManifest>>, CharaError> {
+        let processor_reference = processor_reference.to_string();
+        let processor = processor.clone();
+        let secrets = secrets.clone();
+        let cancellation = cancellation.clone();
+        tokio::task::spawn_blocking(move || {
+            self.get(
+                &processor_reference,
+                &processor,
+                timeout,
+                &secrets,
+                &cancellation,
+            )
+        })
+        .await
+        .map_err(|err| CharaError::Process(err.to_string()))?
+    }
+}
+
+/// Reads a manifest file, the command source is run by the callers.
+fn read_manifest(
+    processor_reference: &str,
+    source: Option<&ManifestSource>,
+    location: Option<&str>,
+) -> Result<Option<ProcessorManifestDto>, CharaError> {
+    match source {
+        Some(ManifestSource::File(path)) => {
+            let path = resolve_path(path, location)?;
+            info!(
+                "Read manifest of processor {processor_reference} from {}",
+                path.display()
+            );
+            File::open(path)
+                .map_err(CharaError::IO)
+                .and_then(|file| {
+                    serde_json::from_reader(BufReader::new(file)).map_err(|err| {
+                        CharaError::InvalidManifest(
+                            processor_reference.to_string(),
+                            err.to_string(),
+                        )
+                    })
+                })
+                .map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+fn parse_manifest(
+    processor_reference: &str,
+    stdout: &str,
+) -> Result<Option<ProcessorManifestDto>, CharaError> {
+    serde_json::from_str(stdout).map(Some).map_err(|err| {
+        CharaError::InvalidManifest(processor_reference.to_string(), err.to_string())
+    })
+}
+
+fn store(
+    processor_reference: &str,
+    outcome: &mut Option<Result<Option<Arc<ProcessorManifest>>, String>>,
+    result: Result<Option<ProcessorManifestDto>, CharaError>,
+) -> Result<Option<Arc<ProcessorManifest>>, CharaError> {
+    let result = result.and_then(|dto| {
+        dto.map(|dto| ProcessorManifest::new(processor_reference, dto).map(Arc::new))
+            .transpose()
+    });
+    *outcome = Some(
+        result
+            .as_ref()
+            .map(Clone::clone)
+            .map_err(|err| err.to_string()),
+    );
+    result
+}
+
+fn previous_outcome(
+    processor_reference: &str,
+    previous: &Result<Option<Arc<ProcessorManifest>>, String>,
+) -> Result<Option<Arc<ProcessorManifest>>, CharaError> {
+    previous.clone().map_err(|err| {
+        CharaError::Process(format!("Manifest of {processor_reference} failed {err}"))
+    })
+}
@@ -1,12 +1,17 @@
 use std::collections::HashMap;
 
 use common::ThreadError;
-use engine::{definition::definition::Definition, errors::CharaError};
+use engine::{
+    definition::definition::Definition,
+    errors::CharaError,
+    processor::{ManifestSource, Protocol},
+};
 
 use crate::{
     dto::definition::{
-        DefinitionDto, EdgeDto, ForeignDefinitionDto, InstallDto, MetadataDto, MetadataEdge,
-        ProcessorDto, ProcessorOverrideDto, ReferenceOrObjectDto, TagDto,
+        DefinitionDto, EdgeDto, ForeignDefinitionDto, InstallDto, ManifestSourceDto, MetadataDto,
+        MetadataEdge, ProcessorDto, ProcessorOverrideDto, ProtocolDto, ReferenceOrObjectDto,
+        RetryDto, TagDto,
     },
     mappers::{arguments::from_arguments, environments::from_environments, tags::from_tags},
 };
@@ -118,7 +123,25 @@ impl DefinitionDto {
                             environments: from_environments(install.environments.clone()),
                             current_directory: install.current_directory.clone(),
                             program: install.program.clone(),
+                            timeout: install.timeout.map(|timeout| timeout.as_secs()),
+                            check: install.check.clone(),
+                        }),
+                        timeout: processor.timeout.map(|timeout| timeout.as_secs()),
+                        retry: processor.retry.as_ref().map(|retry| RetryDto {
+                            attempts: retry.attempts,
+                            backoff: retry.backoff.as_millis().try_into().unwrap_or(u64::MAX),
+                            exit_codes: retry.exit_codes.clone(),
+                        }),
+                        protocol: match processor.protocol {
+                            Protocol::Arguments => None,
+                            Protocol::Stdio { version } => Some(ProtocolDto::Stdio { version }),
+                            Protocol::JsonRpc => Some(ProtocolDto::JsonRpc),
+                        },
+                        manifest: processor.manifest.as_ref().map(|manifest| match manifest {
+                            ManifestSource::Command => ManifestSourceDto::Command(true),
+                            ManifestSource::File(path) => ManifestSourceDto::File(path.clone()),
                         }),
+                        network: processor.network.then_some(true),
                     },
                 ))
             })
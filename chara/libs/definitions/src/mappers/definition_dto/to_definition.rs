@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path, sync::Arc};
+use std::{collections::HashMap, path, sync::Arc, time::Duration};
 
 use engine::{
     definition::definition::Definition,
@@ -10,7 +10,7 @@ use engine::{
         metadata::Metadata,
         tag::{RefTag, Tag},
     },
-    processor::{DraftProcessorOverride, Processor},
+    processor::{DraftProcessorOverride, ManifestSource, Processor, Protocol, RetryPolicy},
     reference_value::{LazyRef, LazyRefOrValue, ReferencedValue},
 };
 
@@ -19,7 +19,9 @@ use serde_json::Value;
 use uuid::Uuid;
 
 use crate::{
-    dto::definition::{DefinitionDto, ForeignDefinitionDto, ReferenceOrObjectDto},
+    dto::definition::{
+        DefinitionDto, ForeignDefinitionDto, ManifestSourceDto, ProtocolDto, ReferenceOrObjectDto,
+    },
     mappers::{
         arguments::to_arguments,
         environments::to_environments,
@@ -114,9 +116,32 @@ impl DefinitionDto {
                             environments: to_environments(&install.environments, &definition),
                             program: install.program.clone(),
                             current_directory: install.current_directory.clone(),
+                            timeout: install.timeout.map(Duration::from_secs),
+                            check: install.check.clone(),
+                            location: definition.location.clone(),
                         }),
                         environments: to_environments(&processor.environments, &definition),
                         current_directory: processor.current_directory.clone(),
+                        timeout: processor.timeout.map(Duration::from_secs),
+                        retry: processor.retry.as_ref().map(|retry| RetryPolicy {
+                            attempts: retry.attempts.max(1),
+                            backoff: Duration::from_millis(retry.backoff),
+                            exit_codes: retry.exit_codes.clone(),
+                        }),
+                        protocol: match processor.protocol {
+                            Some(ProtocolDto::Stdio { version }) => Protocol::Stdio { version },
+                            Some(ProtocolDto::JsonRpc) => Protocol::JsonRpc,
+                            Some(ProtocolDto::Arguments) | None => Protocol::Arguments,
+                        },
+                        manifest: match &processor.manifest {
+                            Some(ManifestSourceDto::Command(true)) => Some(ManifestSource::Command),
+                            Some(ManifestSourceDto::File(path)) => {
+                                Some(ManifestSource::File(path.clone()))
+                            }
+                            Some(ManifestSourceDto::Command(false)) | None => None,
+                        },
+                        location: definition.location.clone(),
+                        network: processor.network.unwrap_or(false),
                     }),
                 )
             })
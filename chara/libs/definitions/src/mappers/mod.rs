@@ -3,5 +3,6 @@ const REFERENCE_PREFIX: &str = "#/";
 mod arguments;
 pub mod definition_dto;
 mod environments;
+mod plan;
 mod processors;
 mod tags;
@@ -0,0 +1,110 @@
+use std::fs::canonicalize;
+
+use common::ThreadError;
+use engine::{
+    contexts::ProcessorContext,
+    definition::{definition::Definition, input::BaseDefinitionInput},
+    errors::CharaError,
+};
+
+use crate::{
+    cli::Cli,
+    dto::plan::{
+        PlanDto, PlannedCommandDto, PlannedForeignDefinitionDto, PlannedInputDto,
+        PlannedProcessorDto,
+    },
+    secret::Secrets,
+    template::Placeholders,
+};
+
+impl PlanDto {
+    pub fn from_definition(definition: &Definition) -> Result<Self, CharaError> {
+        Ok(PlanDto {
+            processors: definition
+                .processors_contexts()
+                .into_iter()
+                .map(PlannedProcessorDto::from_context)
+                .collect::<Result<Vec<_>, CharaError>>()?,
+            foreign_definitions: PlannedForeignDefinitionDto::from_definition(definition)?,
+        })
+    }
+}
+
+impl PlannedCommandDto {
+    fn from_cli(
+        processor_reference: String,
+        cli: &impl Cli,
+        placeholders: &Placeholders,
+    ) -> Result<Self, CharaError> {
+        let current_directory = cli
+            .resolved_current_directory(placeholders)?
+            .map(|current_directory| canonicalize(&current_directory).unwrap_or(current_directory));
+        let program = cli.resolved_program(current_directory.as_deref())?;
+        Ok(PlannedCommandDto {
+            processor_reference,
+            program: program.to_string_lossy().to_string(),
+            current_directory: current_directory
+                .map(|current_directory| current_directory.to_string_lossy().to_string()),
+            arguments: cli
+                .flatten_arguments()
+                .iter()
+                .map(|argument| placeholders.mask(argument))
+                .collect::<Result<_, CharaError>>()?,
+            environments: cli
+                .flatten_environments()
+                .into_iter()
+                .map(|(key, value)| placeholders.mask(&value).map(|value| (key, value)))
+                .collect::<Result<_, CharaError>>()?,
+        })
+    }
+}
+
+impl PlannedProcessorDto {
+    fn from_context(context: ProcessorContext) -> Result<Self, CharaError> {
+        let command = PlannedCommandDto::from_cli(
+            context.definition.processor_reference.clone(),
+            &context.processor,
+            &Placeholders::new(&context.definition, &Secrets::default()),
+        )?;
+        Ok(PlannedProcessorDto {
+            command,
+            context: context.definition,
+        })
+    }
+}
+
+impl PlannedForeignDefinitionDto {
+    fn from_definition(definition: &Definition) -> Result<Vec<Self>, CharaError> {
+        let mut foreign_definitions = vec![];
+        for (key, foreign_definition) in &definition.foreign_definitions {
+            let foreign_definition = foreign_definition
+                .read()
+                .or(Err(CharaError::Thread(ThreadError::Poison)))?;
+            let Some(input) = foreign_definition
+                .input
+                .as_ref()
+                .and_then(|input| input.to_defined())
+            else {
+                continue;
+            };
+            let input = match input {
+                BaseDefinitionInput::File(path) => PlannedInputDto::File(path),
+                BaseDefinitionInput::Id(id) => PlannedInputDto::Id(id),
+                BaseDefinitionInput::Text(text) => PlannedInputDto::Text(text),
+                BaseDefinitionInput::Value(value) => PlannedInputDto::Value(value),
+                BaseDefinitionInput::Processor(processor) => {
+                    PlannedInputDto::Processor(PlannedCommandDto::from_cli(
+                        processor.processor.r#ref.clone(),
+                        &processor,
+                        &Placeholders::default(),
+                    )?)
+                }
+            };
+            foreign_definitions.push(PlannedForeignDefinitionDto {
+                key: key.clone(),
+                input,
+            });
+        }
+        Ok(foreign_definitions)
+    }
+}
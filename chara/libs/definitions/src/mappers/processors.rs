@@ -4,7 +4,7 @@ use engine::{
 
 use crate::dto::definition::{ProcessorOverrideDto, ReferenceOrObjectDto};
 
-use super::{arguments::to_arguments, environments::to_environments, REFERENCE_PREFIX};
+use super::{arguments::to_arguments, environments::to_environments};
 
 pub fn to_node_processor(
     node_processor: &ReferenceOrObjectDto<ProcessorOverrideDto>,
@@ -12,8 +12,7 @@ pub fn to_node_processor(
 ) -> DraftProcessorOverride {
     match node_processor {
         ReferenceOrObjectDto::Reference(reference) => definition
-            .processors
-            .get(reference.trim_start_matches(REFERENCE_PREFIX))
+            .find_processor(reference)
             .map(|processor| {
                 DraftProcessorOverride::processor(&Some(LazyRef::new_referenced_value(
                     reference.clone(),
@@ -35,8 +34,7 @@ pub fn to_processor_override(
 ) -> DraftProcessorOverride {
     if let Some(reference) = processor_override.r#ref.as_ref() {
         definition
-            .processors
-            .get(reference.trim_start_matches(REFERENCE_PREFIX))
+            .find_processor(reference)
             .map(|processor| DraftProcessorOverride {
                 arguments: to_arguments(&processor_override.arguments, definition),
                 environments: to_environments(&processor_override.environments, definition),
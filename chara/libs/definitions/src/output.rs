@@ -0,0 +1,87 @@
+use engine::{
+    contexts::DefinitionContextDto,
+    definition::{definition::Definition, validation::UnresolvedReference},
+    errors::CharaError,
+};
+
+use crate::dto::definition::ProcessorResultDto;
+
+/// Parses the result written by the processor of the context, a wrong shape is reported with its path.
+pub(crate) fn parse_result(
+    content: &str,
+    context: &DefinitionContextDto,
+) -> Result<ProcessorResultDto, CharaError> {
+    let deserializer = &mut serde_json::Deserializer::from_str(content);
+    let result: ProcessorResultDto =
+        serde_path_to_error::deserialize(deserializer).map_err(|err| {
+            invalid_output(
+                context,
+                vec![(err.path().to_string(), err.inner().to_string())],
+            )
+        })?;
+    check_permissions(&result, context)?;
+    Ok(result)
+}
+
+/// Rejects the enrichments the context does not allow the processor to write.
+pub(crate) fn check_permissions(
+    result: &ProcessorResultDto,
+    context: &DefinitionContextDto,
+) -> Result<(), CharaError> {
+    let mut errors = vec![];
+    if let Some(enrichment) = result.enrichment.as_ref() {
+        if enrichment.edge.is_some() && !context.write.edge {
+            errors.push((
+                "enrichment.edge".to_string(),
+                "edge is not writable".to_string(),
+            ));
+        }
+        if enrichment.metadata.is_some() && !context.write.metadata {
+            errors.push((
+                "enrichment.metadata".to_string(),
+                "metadata is not writable".to_string(),
+            ));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid_output(context, errors))
+    }
+}
+
+/// References may still resolve once the definition is linked to its own foreign definitions.
+pub(crate) fn unresolved_references(definition: &Definition, parent: &Definition) -> Vec<String> {
+    definition
+        .validate()
+        .into_iter()
+        .filter(|unresolved| !resolves(parent, unresolved))
+        .map(|unresolved| {
+            format!(
+                "definition.{}.{} : unresolved reference {}",
+                unresolved.key, unresolved.field, unresolved.reference
+            )
+        })
+        .collect()
+}
+
+fn resolves(parent: &Definition, unresolved: &UnresolvedReference) -> bool {
+    let reference = &unresolved.reference;
+    match unresolved.field.as_str() {
+        field if field.ends_with("arguments") => parent.find_argument(reference).is_some(),
+        field if field.ends_with("environments") => parent.find_environment(reference).is_some(),
+        field if field.starts_with("tags.") => parent.find_tag(reference).is_some(),
+        field if field.starts_with("edges.") => parent.find_edge(reference).is_some(),
+        _ => parent.find_processor(reference).is_some(),
+    }
+}
+
+fn invalid_output(context: &DefinitionContextDto, errors: Vec<(String, String)>) -> CharaError {
+    CharaError::InvalidOutput(
+        context.processor_reference.clone(),
+        errors
+            .into_iter()
+            .map(|(path, error)| format!("{path} : {error}"))
+            .collect(),
+    )
+}
@@ -0,0 +1,50 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use engine::{contexts::DefinitionContextDto, errors::CharaError};
+
+use crate::dto::definition::ProcessorResultDto;
+
+/// Scheme of the processor programs run in-process, e.g. `builtin:github`.
+pub const BUILTIN_SCHEME: &str = "builtin:";
+
+pub(crate) fn is_builtin(program: &str) -> bool {
+    program.starts_with(BUILTIN_SCHEME)
+}
+
+/// Processor run in-process instead of spawning its program, registered as `builtin:{name}`.
+pub trait ProcessorPlugin: Send + Sync {
+    fn process(&self, context: &DefinitionContextDto) -> Result<ProcessorResultDto, CharaError>;
+}
+
+/// Plugins keyed by the program of the processors they replace.
+#[derive(Clone, Default)]
+pub struct ProcessorPlugins {
+    plugins: HashMap<String, Arc<dyn ProcessorPlugin>>,
+}
+
+impl ProcessorPlugins {
+    /// Registers the plugin run by the processors whose program is `builtin:{name}`.
+    pub fn register(mut self, name: &str, plugin: impl ProcessorPlugin + 'static) -> Self {
+        self.plugins
+            .insert(format!("{BUILTIN_SCHEME}{name}"), Arc::new(plugin));
+        self
+    }
+
+    /// Plugin of the program, `None` when the program is not a builtin one.
+    pub fn get(&self, program: &str) -> Result<Option<Arc<dyn ProcessorPlugin>>, CharaError> {
+        if !is_builtin(program) {
+            return Ok(None);
+        }
+        self.plugins
+            .get(program)
+            .cloned()
+            .map(Some)
+            .ok_or(CharaError::UnknownPlugin(program.to_string()))
+    }
+}
+
+impl fmt::Debug for ProcessorPlugins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.plugins.keys()).finish()
+    }
+}
@@ -0,0 +1,137 @@
+use std::{
+    cmp::Reverse,
+    env, fs,
+    sync::{Arc, Mutex},
+};
+
+use engine::errors::CharaError;
+
+use crate::cli::{resolve_path, MASK};
+
+pub const SECRET_PREFIX: &str = "secret:";
+const DOTENV_PATH: &str = ".env";
+
+/// Value kept out of the definition, written `secret:env:NAME`, `secret:file:PATH` or `secret:dotenv:NAME`.
+enum Secret<'a> {
+    Env(&'a str),
+    File(&'a str),
+    DotEnv(&'a str),
+}
+
+impl<'a> Secret<'a> {
+    fn parse(value: &'a str) -> Option<Result<Self, CharaError>> {
+        let secret = value.strip_prefix(SECRET_PREFIX)?;
+        Some(match secret.split_once(':') {
+            Some(("env", name)) => Ok(Secret::Env(name)),
+            Some(("file", path)) => Ok(Secret::File(path)),
+            Some(("dotenv", name)) => Ok(Secret::DotEnv(name)),
+            _ => Err(CharaError::Secret(
+                value.to_string(),
+                "expected env, file or dotenv source".to_string(),
+            )),
+        })
+    }
+
+    /// Files are resolved from the directory of the definition declaring the secret.
+    fn read(&self, location: Option<&str>) -> Result<String, String> {
+        let resolve = |path| resolve_path(path, location).map_err(|err| err.to_string());
+        match self {
+            Secret::Env(name) => env::var(name).map_err(|err| err.to_string()),
+            Secret::File(path) => fs::read_to_string(resolve(path)?)
+                .map(|content| content.trim_end_matches(['\r', '\n']).to_string())
+                .map_err(|err| err.to_string()),
+            Secret::DotEnv(name) => {
+                let path = resolve(DOTENV_PATH)?;
+                fs::read_to_string(&path)
+                    .map_err(|err| format!("{} {err}", path.display()))
+                    .and_then(|content| {
+                        dotenv_value(&content, name)
+                            .ok_or(format!("{name} not found in {}", path.display()))
+                    })
+            }
+        }
+    }
+}
+
+fn dotenv_value(content: &str, name: &str) -> Option<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.trim_start_matches("export ").split_once('='))
+        .find(|(key, _)| key.trim() == name)
+        .map(|(_, value)| {
+            let value = value.trim();
+            value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or(value
+                    .strip_prefix('\'')
+                    .and_then(|value| value.strip_suffix('\'')))
+                .unwrap_or(value)
+                .to_string()
+        })
+}
+
+pub fn is_secret(value: &str) -> bool {
+    value.starts_with(SECRET_PREFIX)
+}
+
+/// Values revealed by one `Definitions`, masked in the logs and errors of its processors.
+#[derive(Clone, Debug, Default)]
+pub struct Secrets {
+    revealed: Arc<Mutex<Vec<String>>>,
+}
+
+impl Secrets {
+    /// Reads the secret the value refers to, other values are returned as is.
+    pub fn reveal(&self, value: &str, location: Option<&str>) -> Result<String, CharaError> {
+        let Some(secret) = Secret::parse(value) else {
+            return Ok(value.to_string());
+        };
+        let revealed = secret?
+            .read(location)
+            .map_err(|err| CharaError::Secret(value.to_string(), err))?;
+        if !revealed.is_empty() {
+            if let Ok(mut values) = self.revealed.lock() {
+                if !values.contains(&revealed) {
+                    values.push(revealed.clone());
+                    values.sort_by_key(|value| Reverse(value.len()));
+                }
+            }
+        }
+        Ok(revealed)
+    }
+
+    /// Replaces every revealed secret found in the text.
+    pub fn mask(&self, text: &str) -> String {
+        self.revealed.lock().map_or(text.to_string(), |values| {
+            values
+                .iter()
+                .fold(text.to_string(), |text, value| mask_value(&text, value))
+        })
+    }
+}
+
+/// Secrets shorter than this are masked only as whole tokens, not inside longer words.
+const SHORT_SECRET_LENGTH: usize = 8;
+
+fn mask_value(text: &str, value: &str) -> String {
+    let is_word = |character: Option<char>| {
+        character.is_some_and(|character| character.is_alphanumeric() || character == '_')
+    };
+    let short = value.chars().count() < SHORT_SECRET_LENGTH;
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(value) {
+        let end = start + value.len();
+        let embedded = short
+            && ((is_word(value.chars().next()) && is_word(rest[..start].chars().next_back()))
+                || (is_word(value.chars().next_back()) && is_word(rest[end..].chars().next())));
+        masked.push_str(&rest[..start]);
+        masked.push_str(if embedded { value } else { MASK });
+        rest = &rest[end..];
+    }
+    masked.push_str(rest);
+    masked
+}
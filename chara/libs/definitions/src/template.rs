@@ -0,0 +1,117 @@
+use std::{env, path::Path};
+
+use engine::{
+    contexts::{ContextDto, DefinitionContextDto},
+    errors::CharaError,
+};
+use serde_json::Value;
+
+use crate::{
+    cli::MASK,
+    secret::{is_secret, Secrets},
+};
+
+/// Values of the `{{...}}` placeholders of processor arguments, environments and current directory.
+/// Without context only `{{env.NAME}}` is known.
+#[derive(Default)]
+pub struct Placeholders<'a> {
+    context: Option<&'a DefinitionContextDto>,
+    secrets: Secrets,
+}
+
+impl<'a> Placeholders<'a> {
+    pub fn new(context: &'a DefinitionContextDto, secrets: &Secrets) -> Self {
+        Self {
+            context: Some(context),
+            secrets: secrets.clone(),
+        }
+    }
+
+    pub fn without_context(secrets: &Secrets) -> Self {
+        Self {
+            context: None,
+            secrets: secrets.clone(),
+        }
+    }
+
+    pub fn secrets(&self) -> &Secrets {
+        &self.secrets
+    }
+
+    /// Reveals a secret value, renders the placeholders of any other value.
+    pub fn resolve(&self, value: &str, location: Option<&str>) -> Result<String, CharaError> {
+        if is_secret(value) {
+            self.secrets.reveal(value, location)
+        } else {
+            self.render(value)
+        }
+    }
+
+    /// Masks a secret value without reading it, renders the placeholders of any other value.
+    pub fn mask(&self, value: &str) -> Result<String, CharaError> {
+        if is_secret(value) {
+            Ok(MASK.to_string())
+        } else {
+            self.render(value)
+        }
+    }
+
+    pub fn render(&self, text: &str) -> Result<String, CharaError> {
+        let mut rendered = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            rendered.push_str(&rest[..start]);
+            let placeholder = &rest[start + 2..];
+            let Some(end) = placeholder.find("}}") else {
+                return Err(CharaError::Placeholder(
+                    format!("{{{{{placeholder}"),
+                    text.to_string(),
+                ));
+            };
+            let name = placeholder[..end].trim();
+            let value = self
+                .value(name)
+                .ok_or_else(|| CharaError::Placeholder(name.to_string(), text.to_string()))?;
+            rendered.push_str(&value);
+            rest = &placeholder[end + 2..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        if let Some(variable) = name.strip_prefix("env.") {
+            return env::var(variable).ok();
+        }
+        let context = self.context?;
+        match name.split_once('.')? {
+            ("definition", "location") => context.location.clone(),
+            ("definition", "location_dir") => context
+                .location
+                .as_ref()
+                .and_then(|location| Path::new(location).parent())
+                .map(|directory| directory.to_string_lossy().to_string()),
+            ("metadata", path) => context_value(&context.metadata, path),
+            ("edge", path) => context
+                .edge
+                .as_ref()
+                .and_then(|edge| context_value(edge, path)),
+            _ => None,
+        }
+    }
+}
+
+/// `name` or a path in the context value, `value.` being optional, e.g. `file` or `value.repository.owner`.
+fn context_value(context: &ContextDto, path: &str) -> Option<String> {
+    if path == "name" {
+        return Some(context.name.clone());
+    }
+    let mut segments = path.strip_prefix("value.").unwrap_or(path).split('.');
+    let first = context.value.get(segments.next()?)?;
+    segments
+        .try_fold(first, |value, segment| value.get(segment))
+        .map(|value| match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        })
+}
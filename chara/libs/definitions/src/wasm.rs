@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, OnceLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::ThreadError;
+use engine::{errors::CharaError, processor::RetryPolicy};
+use log::info;
+use tokio_util::sync::CancellationToken;
+use wasmtime::{Config, Engine, Linker, Module, Store, UpdateDeadline};
+use wasmtime_wasi::{
+    pipe::{MemoryInputPipe, MemoryOutputPipe},
+    preview1::{self, WasiP1Ctx},
+    DirPerms, FilePerms, I32Exit, WasiCtxBuilder,
+};
+
+use crate::{cli::retried, secret::Secrets};
+
+/// Extensions of the programs run by the embedded runtime, `wat` being the text format of `wasm`.
+const WASM_EXTENSIONS: [&str; 2] = ["wasm", "wat"];
+/// Directory of the definition as seen by the module.
+const GUEST_DIRECTORY: &str = ".";
+const OUTPUT_CAPACITY: usize = 64 * 1024 * 1024;
+const INTERRUPT_POLL: Duration = Duration::from_millis(50);
+
+pub(crate) fn is_wasm(program: &str) -> bool {
+    Path::new(program)
+        .extension()
+        .is_some_and(|extension| WASM_EXTENSIONS.iter().any(|wasm| extension == *wasm))
+}
+
+/// WASI module run with the context on stdin, reading only the directory of its definition.
+pub(crate) struct WasmCommand {
+    program: PathBuf,
+    arguments: Vec<String>,
+    environments: Vec<(String, String)>,
+    input: String,
+    directory: Option<PathBuf>,
+    network: bool,
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+}
+
+impl WasmCommand {
+    pub fn new(
+        command: &Command,
+        input: String,
+        location: Option<&str>,
+        network: bool,
+        timeout: Option<Duration>,
+        retry: Option<RetryPolicy>,
+    ) -> Self {
+        let program = PathBuf::from(command.get_program());
+        WasmCommand {
+            arguments: [command.get_program()]
+                .into_iter()
+                .chain(command.get_args())
+                .map(|argument| argument.to_string_lossy().to_string())
+                .collect(),
+            environments: command
+                .get_envs()
+                .filter_map(|(key, value)| {
+                    Some((
+                        key.to_string_lossy().to_string(),
+                        value?.to_string_lossy().to_string(),
+                    ))
+                })
+                .collect(),
+            input,
+            directory: location
+                .and_then(|location| Path::new(location).parent())
+                .map(Path::to_path_buf),
+            network,
+            timeout,
+            retry,
+            program,
+        }
+    }
+}
+
+/// Runtime of the wasm processors, each module being compiled once per run.
+#[derive(Default)]
+pub(crate) struct WasmRuntime {
+    engine: OnceLock<Result<Engine, String>>,
+    modules: Mutex<HashMap<PathBuf, Module>>,
+}
+
+impl WasmRuntime {
+    fn engine(&self) -> Result<&Engine, CharaError> {
+        self.engine
+            .get_or_init(|| {
+                let mut config = Config::new();
+                config.epoch_interruption(true);
+                Engine::new(&config).map_err(|err| err.to_string())
+            })
+            .as_ref()
+            .map_err(|err| CharaError::Process(format!("Wasm runtime unavailable {err}")))
+    }
+
+    fn module(&self, program: &Path) -> Result<Module, CharaError> {
+        let mut modules = self
+            .modules
+            .lock()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        if let Some(module) = modules.get(program) {
+            return Ok(module.clone());
+        }
+        info!("Compile wasm processor {}", program.display());
+        let module = Module::from_file(self.engine()?, program).map_err(|err| {
+            CharaError::Process(format!("{} not loaded {err}", program.display()))
+        })?;
+        modules.insert(program.to_path_buf(), module.clone());
+        Ok(module)
+    }
+
+    /// Runs the module until it exits, times out or the run is cancelled, returns its stdout.
+    pub fn stdout(
+        &self,
+        command: WasmCommand,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let program = command.program.display().to_string();
+        retried(command.retry.as_ref(), &program, || {
+            self.run(&command, secrets, cancellation)
+        })
+    }
+
+    fn run(
+        &self,
+        command: &WasmCommand,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let program = command.program.display().to_string();
+        info!("Run wasm program {program}");
+        let module = self.module(&command.program)?;
+        let engine = self.engine()?;
+        let stdout = MemoryOutputPipe::new(OUTPUT_CAPACITY);
+        let stderr = MemoryOutputPipe::new(OUTPUT_CAPACITY);
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(command.input.clone()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .args(&command.arguments)
+            .envs(&command.environments);
+        if let Some(directory) = command.directory.as_ref() {
+            wasi.preopened_dir(directory, GUEST_DIRECTORY, DirPerms::READ, FilePerms::READ)
+                .map_err(|err| {
+                    CharaError::Process(format!("{} not opened {err}", directory.display()))
+                })?;
+        }
+        if command.network {
+            wasi.inherit_network().allow_ip_name_lookup(true);
+        }
+        let mut linker = Linker::<WasiP1Ctx>::new(engine);
+        preview1::add_to_linker_sync(&mut linker, |wasi| wasi)
+            .map_err(|err| CharaError::Process(err.to_string()))?;
+        let mut store = Store::new(engine, wasi.build_p1());
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        store.set_epoch_deadline(1);
+        store.epoch_deadline_callback(move |_| {
+            if stopped.load(Ordering::SeqCst) {
+                Err(wasmtime::Error::msg("Interrupted"))
+            } else {
+                Ok(UpdateDeadline::Continue(1))
+            }
+        });
+
+        let (done, interrupted) =
+            interrupt(engine.clone(), stop, command.timeout, cancellation.clone());
+        let result = linker
+            .module(&mut store, "", &module)
+            .and_then(|linker| linker.get_default(&mut store, ""))
+            .and_then(|start| start.typed::<(), ()>(&store))
+            .and_then(|start| start.call(&mut store, ()));
+        drop(done);
+        let interrupted = interrupted.join().unwrap_or(None);
+
+        let stderr = secrets.mask(&String::from_utf8_lossy(&stderr.contents()));
+        if !stderr.is_empty() {
+            info!("Stderr {stderr}");
+        }
+        let code = match result {
+            Ok(()) => 0,
+            Err(err) => match (err.downcast_ref::<I32Exit>(), interrupted) {
+                (Some(I32Exit(code)), _) => *code,
+                (None, Some(Interruption::Cancelled)) => return Err(CharaError::Cancelled),
+                (None, Some(Interruption::Timeout(timeout))) => {
+                    return Err(CharaError::Timeout(program, timeout))
+                }
+                (None, None) => {
+                    return Err(CharaError::ProcessExit(
+                        None,
+                        secrets.mask(&err.to_string()),
+                    ))
+                }
+            },
+        };
+        if code != 0 {
+            return Err(CharaError::ProcessExit(Some(code), stderr));
+        }
+        String::from_utf8(stdout.contents().to_vec())
+            .map_err(CharaError::ParseUtf8)
+            .inspect(|stdout| info!("Stdout {}", secrets.mask(stdout)))
+    }
+
+    pub async fn stdout_async(
+        self: Arc<Self>,
+        command: WasmCommand,
+        secrets: &Secrets,
+        cancellation: &CancellationToken,
+    ) -> Result<String, CharaError> {
+        let secrets = secrets.clone();
+        let cancellation = cancellation.clone();
+        tokio::task::spawn_blocking(move || self.stdout(command, &secrets, &cancellation))
+            .await
+            .map_err(|err| CharaError::Process(err.to_string()))?
+    }
+}
+
+enum Interruption {
+    Cancelled,
+    Timeout(Duration),
+}
+
+/// Stops the module once the timeout elapses or the run is cancelled, until the sender is dropped.
+/// The epoch is shared by the modules of the engine, the others continue.
+fn interrupt(
+    engine: Engine,
+    stop: Arc<AtomicBool>,
+    timeout: Option<Duration>,
+    cancellation: CancellationToken,
+) -> (mpsc::Sender<()>, thread::JoinHandle<Option<Interruption>>) {
+    let (done, receiver) = mpsc::channel::<()>();
+    let start = Instant::now();
+    let handle = thread::spawn(move || loop {
+        if let Err(mpsc::RecvTimeoutError::Disconnected) = receiver.recv_timeout(INTERRUPT_POLL) {
+            return None;
+        }
+        let interruption = if cancellation.is_cancelled() {
+            Interruption::Cancelled
+        } else if let Some(timeout) = timeout.filter(|timeout| start.elapsed() >= *timeout) {
+            Interruption::Timeout(timeout)
+        } else {
+            continue;
+        };
+        stop.store(true, Ordering::SeqCst);
+        engine.increment_epoch();
+        return Some(interruption);
+    });
+    (done, handle)
+}
@@ -0,0 +1,100 @@
+use std::{fs, thread, time::Duration};
+
+use common::{
+    definition::{self, processed_definition},
+    temp_path,
+};
+use definitions::{
+    cache::ResultCache,
+    definitions::{Definitions, DefinitionsOptions},
+};
+use serde_json::json;
+
+mod common;
+
+fn key(context: &str, environment_keys: &[&str]) -> String {
+    let environment_keys = environment_keys
+        .iter()
+        .map(|key| key.to_string())
+        .collect::<Vec<String>>();
+    ResultCache::key(
+        context,
+        "./github",
+        Some("../scrappers/target/debug"),
+        &["--app-id".to_string(), "1049213".to_string()],
+        environment_keys.iter(),
+    )
+}
+
+#[test]
+fn should_ignore_environment_keys_order() {
+    assert_eq!(
+        key("{}", &["GITHUB_TOKEN", "HTTP_PROXY"]),
+        key("{}", &["HTTP_PROXY", "GITHUB_TOKEN"])
+    );
+}
+
+#[test]
+fn should_change_key_with_context() {
+    assert_ne!(
+        key(r#"{"file":"build.yaml"}"#, &[]),
+        key(r#"{"file":"deploy.yaml"}"#, &[])
+    );
+}
+
+fn enrich(cache: ResultCache, counter: &str) -> usize {
+    let definition = processed_definition(
+        "count",
+        json!({
+            "program": "sh",
+            "arguments": [
+                "-c",
+                "cat > /dev/null; n=$(cat $0 2>/dev/null || echo 0); n=$((n+1)); echo $n > $0; echo '{\"enrichment\":{}}'",
+                counter
+            ],
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+    let definitions = Definitions::new(DefinitionsOptions {
+        cache,
+        ..Default::default()
+    });
+    definition::enrich(&definitions, definition).unwrap();
+    fs::read_to_string(counter).unwrap().trim().parse().unwrap()
+}
+
+#[test]
+fn should_skip_processor_with_cached_result() {
+    let counter = temp_path("cache_hit");
+
+    enrich(ResultCache::default(), &counter);
+
+    assert_eq!(1, enrich(ResultCache::default(), &counter));
+}
+
+#[test]
+fn should_run_processor_once_cached_result_expired() {
+    let counter = temp_path("cache_expired");
+    let cache = ResultCache {
+        ttl: Duration::from_millis(100),
+        ..Default::default()
+    };
+
+    enrich(cache.clone(), &counter);
+    thread::sleep(Duration::from_millis(200));
+
+    assert_eq!(2, enrich(cache, &counter));
+}
+
+#[test]
+fn should_bypass_cache_with_no_cache() {
+    let counter = temp_path("cache_bypass");
+    let cache = ResultCache {
+        no_cache: true,
+        ..Default::default()
+    };
+
+    enrich(ResultCache::default(), &counter);
+
+    assert_eq!(2, enrich(cache, &counter));
+}
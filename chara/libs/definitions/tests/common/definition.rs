@@ -0,0 +1,25 @@
+use ::common::thread::readonly;
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use engine::{errors::CharaError, processor::ProcessorResult, Definitions as ForeignDefinitions};
+use serde_json::{json, Value};
+
+/// Definition whose `build` metadata is enriched by `processor` through the `workflows` edge.
+pub fn processed_definition(processor_name: &str, processor: Value) -> Value {
+    json!({
+        "name": processor_name,
+        "metadata": {
+            "build": { "edges": [{ "ref": "#/workflows" }] }
+        },
+        "edges": {
+            "workflows": { "processor": format!("#/{processor_name}") }
+        },
+        "processors": { processor_name: processor }
+    })
+}
+
+pub fn enrich(definitions: &Definitions, definition: Value) -> Result<ProcessorResult, CharaError> {
+    let definition: DefinitionDto = serde_json::from_value(definition).unwrap();
+    let definition = Definitions::get_from_definition(definition).unwrap();
+    let context = definition.processors_contexts().pop().unwrap();
+    ForeignDefinitions::enrich(definitions, &context, readonly(definition))
+}
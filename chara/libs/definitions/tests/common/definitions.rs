@@ -0,0 +1,18 @@
+use definitions::{
+    cache::ResultCache,
+    definitions::{Definitions, DefinitionsOptions},
+};
+
+pub fn uncached_options() -> DefinitionsOptions {
+    DefinitionsOptions {
+        cache: ResultCache {
+            no_cache: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+pub fn uncached_definitions() -> Definitions {
+    Definitions::new(uncached_options())
+}
@@ -0,0 +1,12 @@
+#![allow(dead_code)]
+pub mod definition;
+pub mod definitions;
+pub mod processor;
+
+use std::{env, fs};
+
+pub fn temp_path(name: &str) -> String {
+    let path = env::temp_dir().join(format!("chara_{name}_{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    path.to_string_lossy().to_string()
+}
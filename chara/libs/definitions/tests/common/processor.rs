@@ -0,0 +1,28 @@
+use ::common::thread::readonly;
+use engine::{
+    processor::{DefinedProcessorOverride, Processor, Protocol},
+    reference_value::{LazyRefOrValue, ReferencedValue},
+};
+
+pub fn processor(program: &str, arguments: Vec<String>) -> Processor {
+    Processor {
+        arguments: vec![LazyRefOrValue::Value(arguments)],
+        program: program.to_string(),
+        install: None,
+        environments: vec![],
+        current_directory: None,
+        timeout: None,
+        retry: None,
+        protocol: Protocol::Arguments,
+        manifest: None,
+        location: None,
+        network: false,
+    }
+}
+
+pub fn defined_processor(reference: &str, processor: Processor) -> DefinedProcessorOverride {
+    DefinedProcessorOverride::processor(&ReferencedValue {
+        r#ref: reference.to_string(),
+        value: readonly(processor),
+    })
+}
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, fs, time::Duration};
+
+use ::common::thread::readonly;
+use common::{
+    definition::{self, processed_definition},
+    definitions::{uncached_definitions, uncached_options},
+    temp_path,
+};
+use definitions::{
+    definitions::{Definitions, DefinitionsOptions},
+    dto::definition::DefinitionDto,
+};
+use engine::{errors::CharaError, Definitions as ForeignDefinitions};
+use serde_json::json;
+
+mod common;
+
+/// Definition whose daemon answers each request with its pid and writes to the marker once asked to shut down.
+fn definition(marker: &str) -> DefinitionDto {
+    let script = format!(
+        r#"while read -r line; do
+            case "$line" in *'"method":"shutdown"'*) echo stopped > {marker}; exit 0;; esac
+            id=$(echo "$line" | sed 's/^{{"jsonrpc":"2.0","id":\([0-9]*\),.*/\1/')
+            echo "{{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{{\"enrichment\":{{\"edge\":{{\"pid\":$$}}}}}}}}"
+        done"#
+    );
+    let mut definition = processed_definition(
+        "daemon",
+        json!({
+            "program": "sh",
+            "arguments": ["-c", script],
+            "protocol": { "name": "jsonrpc" }
+        }),
+    );
+    definition["metadata"]["deploy"] = json!({ "edges": [{ "ref": "#/workflows" }] });
+    serde_json::from_value(definition).unwrap()
+}
+
+#[test]
+fn should_send_every_context_to_one_daemon_until_run_finishes() {
+    let marker = temp_path("daemon_shutdown");
+    let definition = Definitions::get_from_definition(definition(&marker)).unwrap();
+    let definitions = uncached_definitions();
+    let parent = readonly(definition.clone());
+
+    let pids = definition
+        .processors_contexts()
+        .iter()
+        .map(|context| {
+            let result = ForeignDefinitions::enrich(&definitions, context, parent.clone()).unwrap();
+            result.enrichment.unwrap().edge.unwrap()["pid"].clone()
+        })
+        .collect::<Vec<_>>();
+    ForeignDefinitions::finish(&definitions);
+
+    assert_eq!(2, pids.len());
+    assert_eq!(pids[0], pids[1]);
+    assert_eq!("stopped", fs::read_to_string(&marker).unwrap().trim());
+}
+
+#[test]
+fn should_fail_only_timed_out_request() {
+    let starts = temp_path("daemon_starts");
+    let script = format!(
+        r#"echo started >> {starts}
+        while read -r line; do
+            case "$line" in *'"method":"shutdown"'*) exit 0;; esac
+            (
+                id=$(echo "$line" | sed 's/^{{"jsonrpc":"2.0","id":\([0-9]*\),.*/\1/')
+                case "$line" in *'"name":"build"'*) sleep 1;; esac
+                echo "{{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{{}}}}"
+            ) &
+        done"#
+    );
+    let mut definition = processed_definition(
+        "daemon",
+        json!({
+            "program": "sh",
+            "arguments": ["-c", script],
+            "protocol": { "name": "jsonrpc" }
+        }),
+    );
+    definition["metadata"]["deploy"] = json!({ "edges": [{ "ref": "#/workflows" }] });
+    let definition =
+        Definitions::get_from_definition(serde_json::from_value(definition).unwrap()).unwrap();
+    let definitions = Definitions::new(DefinitionsOptions {
+        timeout: Some(Duration::from_millis(300)),
+        ..uncached_options()
+    });
+    let parent = readonly(definition.clone());
+
+    let results = definition
+        .processors_contexts()
+        .iter()
+        .map(|context| {
+            let result = ForeignDefinitions::enrich(&definitions, context, parent.clone());
+            (context.definition.metadata.name.clone(), result)
+        })
+        .collect::<HashMap<_, _>>();
+    ForeignDefinitions::finish(&definitions);
+
+    assert!(matches!(results["build"], Err(CharaError::Timeout(_, _))));
+    assert!(results["deploy"].is_ok());
+    assert_eq!(1, fs::read_to_string(&starts).unwrap().lines().count());
+}
+
+#[test]
+fn should_restart_exited_daemon_on_retry() {
+    let starts = temp_path("daemon_retry_starts");
+    let script = format!(
+        r#"echo started >> {starts}
+        if [ "$(wc -l < {starts})" -eq 1 ]; then read -r line; exit 1; fi
+        while read -r line; do
+            case "$line" in *'"method":"shutdown"'*) exit 0;; esac
+            id=$(echo "$line" | sed 's/^{{"jsonrpc":"2.0","id":\([0-9]*\),.*/\1/')
+            echo "{{\"jsonrpc\":\"2.0\",\"id\":$id,\"result\":{{}}}}"
+        done"#
+    );
+    let definition = processed_definition(
+        "daemon",
+        json!({
+            "program": "sh",
+            "arguments": ["-c", script],
+            "protocol": { "name": "jsonrpc" },
+            "retry": { "attempts": 2, "backoff": 50 }
+        }),
+    );
+    let definitions = uncached_definitions();
+
+    let result = definition::enrich(&definitions, definition);
+    ForeignDefinitions::finish(&definitions);
+
+    assert!(result.is_ok());
+    assert_eq!(2, fs::read_to_string(&starts).unwrap().lines().count());
+}
@@ -0,0 +1,78 @@
+use std::fs;
+
+use common::temp_path;
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use engine::definition::definition::Definition;
+use serde_json::json;
+
+mod common;
+
+/// Definition whose processor installation appends a line to the counter, its check succeeds when the marker exists.
+fn definition(counter: &str, marker: &str) -> Definition {
+    located_definition(counter, marker, None)
+}
+
+fn located_definition(counter: &str, marker: &str, location: Option<&str>) -> Definition {
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "install",
+        "location": location,
+        "processors": {
+            "sh": {
+                "program": "sh",
+                "install": {
+                    "program": "sh",
+                    "arguments": ["-c", format!("echo installed >> {counter}")],
+                    "check": ["-c", format!("test -f {marker}")]
+                }
+            }
+        }
+    }))
+    .unwrap();
+    Definitions::get_from_definition(definition).unwrap()
+}
+
+#[test]
+fn should_install_processor_once() {
+    let counter = temp_path("install_once");
+    let definition = definition(&counter, &temp_path("install_once_marker"));
+    let definitions = Definitions::default();
+
+    let first = definitions.install_processors(&definition);
+    let second = definitions.install_processors(&definition);
+
+    assert_eq!("#/sh", first[0].0);
+    assert!(first[0].1.is_ok() && second[0].1.is_ok());
+    assert_eq!(1, fs::read_to_string(&counter).unwrap().lines().count());
+}
+
+#[test]
+fn should_skip_installation_when_check_succeeds() {
+    let counter = temp_path("install_checked");
+    let marker = temp_path("install_checked_marker");
+    fs::write(&marker, "").unwrap();
+
+    let results = Definitions::default().install_processors(&definition(&counter, &marker));
+
+    assert!(results[0].1.is_ok());
+    assert!(fs::metadata(&counter).is_err());
+}
+
+#[test]
+fn should_install_processors_of_each_declaring_definition() {
+    let counter = temp_path("install_located");
+    let marker = temp_path("install_located_marker");
+    let definitions = Definitions::default();
+
+    definitions.install_processors(&located_definition(
+        &counter,
+        &marker,
+        Some("/a/chara.json"),
+    ));
+    definitions.install_processors(&located_definition(
+        &counter,
+        &marker,
+        Some("/b/chara.json"),
+    ));
+
+    assert_eq!(2, fs::read_to_string(&counter).unwrap().lines().count());
+}
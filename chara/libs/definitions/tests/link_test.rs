@@ -0,0 +1,40 @@
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use serde_json::json;
+
+#[test]
+fn should_link_processor_shipped_by_imported_definition() {
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "root",
+        "metadata": {
+            "repository": { "edges": ["workflows"] }
+        },
+        "edges": {
+            "workflows": {
+                "definition": {
+                    "name": "imported",
+                    "processors": {
+                        "github": { "program": "./github" }
+                    }
+                }
+            },
+            "deploy": { "processor": "repository/workflows/github" }
+        }
+    }))
+    .unwrap();
+    let definition = Definitions::get_from_definition(definition).unwrap();
+    assert_eq!(1, definition.validate().len());
+
+    assert_eq!(1, definition.link().unwrap());
+
+    assert!(definition.validate().is_empty());
+    let edge = definition.edges["deploy"].read().unwrap();
+    let processor = edge
+        .processor
+        .as_ref()
+        .and_then(|processor| processor.map())
+        .unwrap();
+    assert_eq!(
+        "./github",
+        processor.processor.value.read().unwrap().program
+    );
+}
@@ -0,0 +1,101 @@
+use std::{
+    env,
+    fs::{self, canonicalize},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use common::{
+    definition::{enrich, processed_definition},
+    definitions::uncached_definitions,
+};
+use engine::processor::ProcessorResult;
+use serde_json::{json, Value};
+
+mod common;
+
+const SCRIPT: &str =
+    "#!/bin/sh\ncat > /dev/null\necho \"{\\\"enrichment\\\":{\\\"edge\\\":{\\\"directory\\\":\\\"$(pwd)\\\"}}}\"\n";
+
+/// Directory holding a definition location and the script its processor runs.
+fn definition_directory() -> PathBuf {
+    let directory = env::temp_dir().join(format!("chara_location_{}", std::process::id()));
+    let scripts = directory.join("scripts");
+    fs::create_dir_all(&scripts).unwrap();
+    let script = scripts.join("directory.sh");
+    fs::write(&script, SCRIPT).unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    directory
+}
+
+/// Runs the processor declared in a definition located in the given directory.
+fn run(directory: &Path, processor: Value) -> ProcessorResult {
+    let mut definition = processed_definition("directory", processor);
+    definition["location"] = json!(directory.join("chara.json"));
+    enrich(&uncached_definitions(), definition).unwrap()
+}
+
+/// Returns the working directory of the processor.
+fn run_directory(directory: &Path, processor: Value) -> String {
+    run(directory, processor).enrichment.unwrap().edge.unwrap()["directory"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn should_resolve_relative_paths_from_definition_location() {
+    let directory = definition_directory();
+
+    let current_directory = run_directory(
+        &directory,
+        json!({
+            "program": "./directory.sh",
+            "currentDirectory": "scripts",
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+
+    assert_eq!(
+        canonicalize(directory.join("scripts")).unwrap(),
+        PathBuf::from(current_directory)
+    );
+}
+
+#[test]
+fn should_resolve_cwd_escape_from_working_directory() {
+    let directory = definition_directory();
+
+    let current_directory = run_directory(
+        &directory,
+        json!({
+            "program": format!("{}/scripts/directory.sh", directory.display()),
+            "currentDirectory": "${cwd}",
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+
+    assert_eq!(
+        canonicalize(env::current_dir().unwrap()).unwrap(),
+        PathBuf::from(current_directory)
+    );
+}
+
+#[test]
+fn should_locate_child_definition_at_parent_location() {
+    let directory = definition_directory();
+
+    let result = run(
+        &directory,
+        json!({
+            "program": "sh",
+            "arguments": ["-c", "cat > /dev/null; echo '{\"definition\":{\"name\":\"child\"}}'"],
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+
+    assert_eq!(
+        directory.join("chara.json").to_str(),
+        result.definition.unwrap().location.as_deref()
+    );
+}
@@ -0,0 +1,128 @@
+use std::{
+    env, fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use common::thread::readonly;
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use engine::{errors::CharaError, Definitions as ForeignDefinitions};
+use serde_json::{json, Value};
+
+fn manifest() -> Value {
+    json!({
+        "name": "github",
+        "version": "1.0.0",
+        "metadata": {
+            "type": "object",
+            "required": ["file"],
+            "properties": { "file": { "type": "string" } }
+        },
+        "writes": ["definition"]
+    })
+}
+
+/// Definition whose processor requires a `file` in its metadata, the processor itself always fails.
+fn definition(name: &str) -> DefinitionDto {
+    let manifest_path = env::temp_dir().join(format!("chara_{name}_{}.json", std::process::id()));
+    fs::write(&manifest_path, manifest().to_string()).unwrap();
+    located_definition(
+        None,
+        json!({ "program": "false", "manifest": manifest_path }),
+    )
+}
+
+/// Directory holding `manifest.json` and `manifest.sh`, printing it only when called with `--manifest` alone.
+fn manifest_directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("chara_{name}_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join("manifest.json"), manifest().to_string()).unwrap();
+    let script = directory.join("manifest.sh");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\n[ \"$#\" = 1 ] && [ \"$1\" = --manifest ] && echo '{}'\n",
+            manifest()
+        ),
+    )
+    .unwrap();
+    fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    directory
+}
+
+fn located_definition(directory: Option<&Path>, processor: Value) -> DefinitionDto {
+    serde_json::from_value(json!({
+        "name": "manifest",
+        "location": directory.map(|directory| directory.join("chara.json")),
+        "metadata": {
+            "build": { "edges": [{ "ref": "#/workflows" }], "owner": "sbailleul" }
+        },
+        "edges": {
+            "workflows": { "processor": "#/github" }
+        },
+        "processors": {
+            "github": processor
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn should_reject_context_before_spawning_processor() {
+    let definition = Definitions::get_from_definition(definition("manifest_reject")).unwrap();
+    let context = definition.processors_contexts().pop().unwrap();
+
+    let result =
+        ForeignDefinitions::enrich(&Definitions::default(), &context, readonly(definition));
+
+    let Err(CharaError::InvalidContext(processor_reference, error)) = result else {
+        panic!("Context should be rejected by the manifest");
+    };
+    assert_eq!("#/github", processor_reference);
+    assert!(error.contains("file"));
+}
+
+#[test]
+fn should_document_processors_with_their_manifest() {
+    let definition = Definitions::get_from_definition(definition("manifest_document")).unwrap();
+
+    let documentation = Definitions::default().manifests(&definition);
+
+    assert_eq!(1, documentation.len());
+    assert_eq!("#/github", documentation[0].processor_reference);
+    assert_eq!("1.0.0", documentation[0].manifest.as_ref().unwrap().version);
+}
+
+#[test]
+fn should_read_manifest_file_from_definition_directory() {
+    let directory = manifest_directory("manifest_file");
+    let definition = Definitions::get_from_definition(located_definition(
+        Some(&directory),
+        json!({ "program": "false", "manifest": "manifest.json" }),
+    ))
+    .unwrap();
+
+    let documentation = Definitions::default().manifests(&definition);
+
+    assert_eq!(None, documentation[0].error);
+    assert_eq!("1.0.0", documentation[0].manifest.as_ref().unwrap().version);
+}
+
+#[test]
+fn should_run_manifest_command_without_processor_arguments() {
+    let directory = manifest_directory("manifest_command");
+    let definition = Definitions::get_from_definition(located_definition(
+        Some(&directory),
+        json!({
+            "program": "./manifest.sh",
+            "arguments": ["{{metadata.file}}"],
+            "manifest": true
+        }),
+    ))
+    .unwrap();
+
+    let documentation = Definitions::default().manifests(&definition);
+
+    assert_eq!(None, documentation[0].error);
+    assert_eq!("1.0.0", documentation[0].manifest.as_ref().unwrap().version);
+}
@@ -0,0 +1,69 @@
+use common::{
+    definition::{self, processed_definition},
+    definitions::uncached_definitions,
+};
+use engine::errors::CharaError;
+use serde_json::{json, Value};
+
+mod common;
+
+/// Enriches the edge context of a definition whose processor prints the given output.
+fn enrich(output: Value) -> Result<(), CharaError> {
+    let definition = processed_definition(
+        "echo",
+        json!({
+            "program": "sh",
+            "arguments": ["-c", format!("cat > /dev/null; echo '{output}'")],
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+    definition::enrich(&uncached_definitions(), definition).map(|_| ())
+}
+
+fn errors(result: Result<(), CharaError>) -> Vec<String> {
+    let Err(CharaError::InvalidOutput(processor_reference, errors)) = result else {
+        panic!("Output should be rejected");
+    };
+    assert_eq!("#/echo", processor_reference);
+    errors
+}
+
+#[test]
+fn should_reject_enrichment_without_write_permission() {
+    let errors = errors(enrich(
+        json!({ "enrichment": { "metadata": { "file": "build.yaml" } } }),
+    ));
+
+    assert_eq!(1, errors.len());
+    assert!(errors[0].starts_with("enrichment.metadata :"));
+}
+
+#[test]
+fn should_report_path_of_malformed_output() {
+    let errors = errors(enrich(json!({
+        "definition": { "name": "child", "metadata": { "build": { "edges": [42] } } }
+    })));
+
+    assert!(errors[0].starts_with("definition.metadata.build.edges[0] :"));
+}
+
+#[test]
+fn should_accept_references_resolved_by_later_linking() {
+    let result = enrich(json!({
+        "definition": {
+            "name": "child",
+            "metadata": { "repository": { "edges": ["workflows"] } },
+            "edges": {
+                "workflows": {
+                    "definition": {
+                        "name": "imported",
+                        "processors": { "github": { "program": "./github" } }
+                    }
+                },
+                "deploy": { "processor": "repository/workflows/github" }
+            }
+        }
+    }));
+
+    assert!(result.is_ok());
+}
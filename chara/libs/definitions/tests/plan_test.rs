@@ -0,0 +1,134 @@
+use std::{collections::HashMap, path::Path};
+
+use definitions::{
+    definitions::Definitions,
+    dto::{
+        definition::DefinitionDto,
+        plan::{PlanDto, PlannedInputDto},
+    },
+};
+use serde_json::json;
+
+#[test]
+fn should_plan_processors_and_foreign_definitions() {
+    let definition =
+        Definitions::get_from_path("./tests/definitions/plan.json".to_string()).unwrap();
+
+    let plan = PlanDto::from_definition(&definition).unwrap();
+
+    assert_eq!(plan.processors.len(), 1);
+    let processor = &plan.processors[0];
+    assert_eq!(processor.command.processor_reference, "#/github");
+    assert_eq!(
+        processor.command.program,
+        Path::new("./tests/definitions")
+            .join("./github")
+            .to_str()
+            .unwrap()
+    );
+    assert_eq!(processor.context.metadata.name, "build");
+    assert_eq!(
+        processor.command.arguments,
+        vec![
+            "--app-id",
+            "1049213",
+            "--file",
+            ".github/workflows/build-workflow.yaml"
+        ]
+    );
+    assert_eq!(processor.command.environments["GITHUB_TOKEN"], "********");
+    assert_eq!(processor.command.environments["LOG"], "debug");
+    assert_eq!(processor.command.environments["EDGE"], "#/workflows");
+    assert_eq!(plan.foreign_definitions.len(), 1);
+    let PlannedInputDto::Processor(command) = &plan.foreign_definitions[0].input else {
+        panic!("Foreign definition should be loaded by a processor");
+    };
+    assert_eq!(
+        command.program,
+        Path::new("./tests/definitions")
+            .join("./http")
+            .to_str()
+            .unwrap()
+    );
+    assert!(command
+        .arguments
+        .contains(&"https://sbailleul.github.io/chara_public/definition.json".to_string()));
+}
+
+#[test]
+fn should_layer_metadata_edge_inputs_on_edge_processor() {
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "layers",
+        "metadata": {
+            "build": {
+                "edges": [{
+                    "ref": "#/workflows",
+                    "arguments": ["--branch", "main"],
+                    "environments": [{ "LEVEL": "metadata", "BRANCH": "main" }]
+                }]
+            }
+        },
+        "edges": {
+            "workflows": {
+                "processor": {
+                    "ref": "#/github",
+                    "arguments": ["--verbose", "--branch", "dev"],
+                    "environments": [{ "LEVEL": "edge" }]
+                }
+            }
+        },
+        "processors": {
+            "github": { "program": "./github", "arguments": ["--app-id", "1"] }
+        }
+    }))
+    .unwrap();
+
+    let plan =
+        PlanDto::from_definition(&Definitions::get_from_definition(definition).unwrap()).unwrap();
+
+    let command = &plan.processors[0].command;
+    assert_eq!(
+        vec![
+            "--app-id",
+            "1",
+            "--verbose",
+            "--branch",
+            "dev",
+            "--branch",
+            "main"
+        ],
+        command.arguments
+    );
+    assert_eq!(
+        HashMap::from([
+            ("LEVEL".to_string(), "metadata".to_string()),
+            ("BRANCH".to_string(), "main".to_string())
+        ]),
+        command.environments
+    );
+}
+
+#[test]
+fn should_plan_builtin_processor_without_resolving_it() {
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "builtin",
+        "location": "/repository/chara.json",
+        "metadata": {
+            "build": { "edges": [{ "ref": "#/workflows" }] }
+        },
+        "edges": {
+            "workflows": { "processor": "#/github" }
+        },
+        "processors": {
+            "github": { "program": "builtin:github", "arguments": ["--app-id", "1"] }
+        }
+    }))
+    .unwrap();
+
+    let plan =
+        PlanDto::from_definition(&Definitions::get_from_definition(definition).unwrap()).unwrap();
+
+    let command = &plan.processors[0].command;
+    assert_eq!("builtin:github", command.program);
+    assert_eq!(vec!["--app-id", "1"], command.arguments);
+}
@@ -0,0 +1,102 @@
+use common::{
+    definition::{self, processed_definition},
+    definitions::uncached_options,
+    processor::{defined_processor, processor},
+};
+use definitions::{
+    definitions::{Definitions, DefinitionsOptions},
+    dto::definition::{EnrichmentDto, ProcessorResultDto},
+    plugin::{ProcessorPlugin, ProcessorPlugins},
+};
+use engine::{
+    contexts::DefinitionContextDto, definition::input::BaseDefinitionInput, errors::CharaError,
+    processor::ProcessorResult, Definitions as ForeignDefinitions,
+};
+use serde_json::{json, Map};
+
+mod common;
+
+/// Enriches the edge with the name of the metadata.
+struct MetadataName;
+
+impl ProcessorPlugin for MetadataName {
+    fn process(&self, context: &DefinitionContextDto) -> Result<ProcessorResultDto, CharaError> {
+        let mut edge = Map::new();
+        edge.insert("metadata".to_string(), json!(context.metadata.name));
+        Ok(ProcessorResultDto {
+            enrichment: Some(EnrichmentDto {
+                edge: Some(edge),
+                metadata: None,
+            }),
+            definition: None,
+        })
+    }
+}
+
+fn definitions() -> Definitions {
+    Definitions::new(DefinitionsOptions {
+        plugins: ProcessorPlugins::default().register("name", MetadataName),
+        ..uncached_options()
+    })
+}
+
+fn enrich(program: &str) -> Result<ProcessorResult, CharaError> {
+    definition::enrich(
+        &definitions(),
+        processed_definition("name", json!({ "program": program })),
+    )
+}
+
+#[test]
+fn should_run_builtin_processor_in_process() {
+    let result = enrich("builtin:name").unwrap();
+
+    assert_eq!(
+        "build",
+        result.enrichment.unwrap().edge.unwrap()["metadata"]
+    );
+}
+
+#[test]
+fn should_reject_unregistered_builtin_processor() {
+    let result = enrich("builtin:missing");
+
+    assert!(
+        matches!(result, Err(CharaError::UnknownPlugin(program)) if program == "builtin:missing")
+    );
+}
+
+#[test]
+fn should_reject_installation_of_builtin_processor() {
+    let result = definition::enrich(
+        &definitions(),
+        processed_definition(
+            "name",
+            json!({
+                "program": "builtin:name",
+                "install": { "program": "true" }
+            }),
+        ),
+    );
+
+    assert!(matches!(
+        result,
+        Err(CharaError::UnsupportedPlugin(program, _)) if program == "builtin:name"
+    ));
+}
+
+#[test]
+fn should_reject_builtin_definition_processor() {
+    let result = ForeignDefinitions::get(
+        &definitions(),
+        &BaseDefinitionInput::Processor(defined_processor(
+            "#/name",
+            processor("builtin:name", vec![]),
+        )),
+    );
+
+    assert!(matches!(
+        result,
+        Err(CharaError::UnsupportedPlugin(program, _)) if program == "builtin:name"
+    ));
+}
@@ -0,0 +1,41 @@
+use common::thread::readonly;
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use serde_json::json;
+
+fn child(processor_reference: &str) -> DefinitionDto {
+    serde_json::from_value(json!({
+        "name": "child",
+        "edges": {
+            "workflows": { "processor": processor_reference }
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn should_resolve_processor_declared_in_ancestors() {
+    let root: DefinitionDto = serde_json::from_value(json!({
+        "name": "root",
+        "processors": {
+            "github": { "program": "./github" }
+        }
+    }))
+    .unwrap();
+    let root = readonly(Definitions::get_from_definition(root).unwrap());
+
+    for processor_reference in ["#/github", "../github"] {
+        let child = child(processor_reference).map_with_location(None, Some(root.clone()));
+        let edge = child.edges["workflows"].read().unwrap();
+        let processor = edge
+            .processor
+            .as_ref()
+            .and_then(|processor| processor.map())
+            .unwrap();
+
+        assert_eq!(
+            "./github",
+            processor.processor.value.read().unwrap().program
+        );
+        assert!(child.validate().is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+use common::{
+    definition::{enrich, processed_definition},
+    definitions::uncached_definitions,
+};
+use serde_json::{json, Value};
+
+mod common;
+
+/// Definition whose processor answers on stdout once it read a request of the given version from stdin.
+fn definition(version: u32) -> Value {
+    processed_definition(
+        "echo",
+        json!({
+            "program": "sh",
+            "arguments": ["-c", r#"read -r request; echo "reading $request" >&2; case "$request" in *'"version":1'*'"name":"build"'*) echo '{"enrichment":{"edge":{"protocol":"stdio"}}}';; esac"#],
+            "protocol": { "name": "stdio", "version": version }
+        }),
+    )
+}
+
+#[test]
+fn should_exchange_context_and_result_over_stdio() {
+    let result = enrich(&uncached_definitions(), definition(1)).unwrap();
+
+    let edge = result.enrichment.unwrap().edge.unwrap();
+    assert_eq!(Some(&Value::from("stdio")), edge.get("protocol"));
+}
+
+#[test]
+fn should_reject_unsupported_protocol_version() {
+    let result = enrich(&uncached_definitions(), definition(2));
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,60 @@
+use std::{fs, time::Duration};
+
+use common::{
+    processor::{defined_processor, processor},
+    temp_path,
+};
+use definitions::definitions::Definitions;
+use engine::{
+    definition::input::BaseDefinitionInput,
+    errors::CharaError,
+    processor::{DefinedProcessorOverride, Processor, RetryPolicy},
+    Definitions as ForeignDefinitions,
+};
+
+mod common;
+
+/// Processor failing with exit code 75 until its third execution.
+fn flaky_processor(counter: &str, exit_codes: Vec<i32>) -> DefinedProcessorOverride {
+    let script = format!(
+        r#"n=$(cat {counter} 2>/dev/null || echo 0); n=$((n+1)); echo $n > {counter}; [ $n -ge 3 ] && echo '{{"name":"flaky"}}' || exit 75"#
+    );
+    defined_processor(
+        "#/flaky",
+        Processor {
+            retry: Some(RetryPolicy {
+                attempts: 3,
+                backoff: Duration::from_millis(1),
+                exit_codes,
+            }),
+            ..processor("sh", vec!["-c".to_string(), script])
+        },
+    )
+}
+
+#[test]
+fn should_retry_until_processor_succeeds() {
+    let counter = temp_path("retry_success");
+
+    let definition = ForeignDefinitions::get(
+        &Definitions::default(),
+        &BaseDefinitionInput::Processor(flaky_processor(&counter, vec![75])),
+    )
+    .unwrap();
+
+    assert_eq!("flaky", definition.name);
+    assert_eq!("3", fs::read_to_string(&counter).unwrap().trim());
+}
+
+#[test]
+fn should_not_retry_other_exit_codes() {
+    let counter = temp_path("retry_exit_code");
+
+    let result = ForeignDefinitions::get(
+        &Definitions::default(),
+        &BaseDefinitionInput::Processor(flaky_processor(&counter, vec![1])),
+    );
+
+    assert!(matches!(result, Err(CharaError::ProcessExit(Some(75), _))));
+    assert_eq!("1", fs::read_to_string(&counter).unwrap().trim());
+}
@@ -0,0 +1,90 @@
+use std::{env, fs};
+
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto, secret::Secrets};
+use engine::{
+    definition::{definition::Definition, input::BaseDefinitionInput},
+    errors::CharaError,
+    processor::DefinedProcessorOverride,
+    reference_value::ReferencedValue,
+    Definitions as ForeignDefinitions,
+};
+use serde_json::json;
+
+const SECRET: &str = "s3cr3t-t0k3n";
+
+/// Definition whose processor prints its token on stderr before failing.
+fn definition() -> Definition {
+    env::set_var("CHARA_SECRET_TEST_TOKEN", SECRET);
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "secret",
+        "processors": {
+            "leak": {
+                "program": "sh",
+                "arguments": ["-c", "echo \"token $TOKEN\" >&2; exit 1"],
+                "environments": [{ "TOKEN": "secret:env:CHARA_SECRET_TEST_TOKEN" }]
+            }
+        }
+    }))
+    .unwrap();
+    Definitions::get_from_definition(definition).unwrap()
+}
+
+#[test]
+fn should_pass_secret_to_processor_and_mask_it() {
+    let definition = definition();
+    let processor = DefinedProcessorOverride::processor(&ReferencedValue {
+        r#ref: "#/leak".to_string(),
+        value: definition.processors["leak"].clone(),
+    });
+
+    let result = ForeignDefinitions::get(
+        &Definitions::default(),
+        &BaseDefinitionInput::Processor(processor),
+    );
+
+    let Err(CharaError::ProcessExit(_, stderr)) = result else {
+        panic!("Processor should fail");
+    };
+    assert_eq!("token ********", stderr.trim());
+}
+
+#[test]
+fn should_not_serialize_secret_values() {
+    let saved = serde_json::to_string(&DefinitionDto::from_definition(&definition())).unwrap();
+
+    assert!(saved.contains("secret:env:CHARA_SECRET_TEST_TOKEN"));
+    assert!(!saved.contains(SECRET));
+}
+
+#[test]
+fn should_mask_short_secret_only_as_whole_token() {
+    env::set_var("CHARA_SECRET_TEST_PORT", "42");
+    let secrets = Secrets::default();
+
+    secrets
+        .reveal("secret:env:CHARA_SECRET_TEST_PORT", None)
+        .unwrap();
+
+    assert_eq!("port ******** in 2042", secrets.mask("port 42 in 2042"));
+    assert_eq!("port 42", Secrets::default().mask("port 42"));
+}
+
+#[test]
+fn should_read_secret_files_from_definition_directory() {
+    let directory = env::temp_dir().join(format!("chara_secret_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    fs::write(directory.join(".env"), "TOKEN='dotenv-token'\n").unwrap();
+    fs::write(directory.join("token"), "file-token\n").unwrap();
+    let location = directory.join("chara.json");
+    let location = location.to_str();
+    let secrets = Secrets::default();
+
+    assert_eq!(
+        "dotenv-token",
+        secrets.reveal("secret:dotenv:TOKEN", location).unwrap()
+    );
+    assert_eq!(
+        "file-token",
+        secrets.reveal("secret:file:token", location).unwrap()
+    );
+}
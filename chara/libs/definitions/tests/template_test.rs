@@ -0,0 +1,44 @@
+use common::definition::{self, processed_definition};
+use common::definitions::uncached_definitions;
+use engine::{errors::CharaError, processor::ProcessorResult};
+use serde_json::json;
+
+mod common;
+
+/// Enriches the edge context with the processor argument rendered from the given template.
+fn enrich(template: &str) -> Result<ProcessorResult, CharaError> {
+    let mut definition = processed_definition(
+        "echo",
+        json!({
+            "program": "sh",
+            "arguments": [
+                "-c",
+                "cat > /dev/null; echo \"{\\\"enrichment\\\":{\\\"edge\\\":{\\\"argument\\\":\\\"$0\\\"}}}\"",
+                template
+            ],
+            "protocol": { "name": "stdio", "version": 1 }
+        }),
+    );
+    definition["metadata"]["build"]["file"] = json!("build.yaml");
+    definition["edges"]["workflows"]["url"] = json!("https://example.com");
+    definition::enrich(&uncached_definitions(), definition)
+}
+
+#[test]
+fn should_render_placeholders_from_context() {
+    let result = enrich("{{metadata.name}} {{metadata.file}} {{ edge.value.url }}").unwrap();
+
+    assert_eq!(
+        "build build.yaml https://example.com",
+        result.enrichment.unwrap().edge.unwrap()["argument"]
+    );
+}
+
+#[test]
+fn should_reject_unknown_placeholder() {
+    let Err(CharaError::Placeholder(placeholder, _)) = enrich("{{metadata.missing}}") else {
+        panic!("Placeholder should be rejected");
+    };
+
+    assert_eq!("metadata.missing", placeholder);
+}
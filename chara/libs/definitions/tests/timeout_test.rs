@@ -0,0 +1,92 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::processor::{defined_processor, processor};
+use definitions::definitions::{Definitions, DefinitionsOptions};
+use engine::{
+    asynchronous::AsyncDefinitions,
+    definition::input::BaseDefinitionInput,
+    errors::CharaError,
+    processor::{DefinedProcessorOverride, Processor},
+    Definitions as ForeignDefinitions,
+};
+use tokio_util::sync::CancellationToken;
+
+mod common;
+
+fn sleeping_processor(timeout: Option<Duration>) -> DefinedProcessorOverride {
+    defined_processor(
+        "#/sleep",
+        Processor {
+            timeout,
+            ..processor("sleep", vec!["5".to_string()])
+        },
+    )
+}
+
+#[test]
+fn should_kill_processor_after_its_timeout() {
+    let definitions = Definitions::default();
+    let start = Instant::now();
+
+    let result = ForeignDefinitions::get(
+        &definitions,
+        &BaseDefinitionInput::Processor(sleeping_processor(Some(Duration::from_millis(100)))),
+    );
+
+    assert!(matches!(result, Err(CharaError::Timeout(_, _))));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn should_apply_default_timeout() {
+    let definitions = Definitions::new(DefinitionsOptions {
+        timeout: Some(Duration::from_millis(100)),
+        ..Default::default()
+    });
+
+    let result = ForeignDefinitions::get(
+        &definitions,
+        &BaseDefinitionInput::Processor(sleeping_processor(None)),
+    );
+
+    assert!(matches!(result, Err(CharaError::Timeout(_, _))));
+}
+
+#[tokio::test]
+async fn should_kill_processor_after_its_timeout_asynchronously() {
+    let definitions = Definitions::default();
+
+    let result = AsyncDefinitions::get(
+        &definitions,
+        &BaseDefinitionInput::Processor(sleeping_processor(Some(Duration::from_millis(100)))),
+    )
+    .await;
+
+    assert!(matches!(result, Err(CharaError::Timeout(_, _))));
+}
+
+#[test]
+fn should_kill_processor_when_cancelled() {
+    let cancellation = CancellationToken::new();
+    let definitions = Definitions::new(DefinitionsOptions {
+        cancellation: cancellation.clone(),
+        ..Default::default()
+    });
+    let start = Instant::now();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        cancellation.cancel();
+    });
+
+    let result = ForeignDefinitions::get(
+        &definitions,
+        &BaseDefinitionInput::Processor(sleeping_processor(None)),
+    );
+
+    canceller.join().unwrap();
+    assert!(matches!(result, Err(CharaError::Cancelled)));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
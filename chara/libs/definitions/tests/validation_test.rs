@@ -0,0 +1,58 @@
+use definitions::{definitions::Definitions, dto::definition::DefinitionDto};
+use engine::definition::validation::UnresolvedReference;
+use serde_json::json;
+
+fn unresolved(key: &str, field: &str, reference: &str) -> UnresolvedReference {
+    UnresolvedReference {
+        key: key.to_string(),
+        field: field.to_string(),
+        reference: reference.to_string(),
+    }
+}
+
+#[test]
+fn should_list_unresolved_references() {
+    let definition: DefinitionDto = serde_json::from_value(json!({
+        "name": "validation",
+        "metadata": {
+            "build": {
+                "edges": [
+                    "#/worflows",
+                    "#/workflows",
+                    { "ref": "#/releases", "arguments": ["#/arguments/brnch"] }
+                ],
+                "tags": ["#/tags/rust"]
+            }
+        },
+        "edges": {
+            "workflows": { "processor": "#/githb" }
+        },
+        "processors": {
+            "github": {
+                "program": "./github",
+                "arguments": ["--verbose", "#/arguments/tokn"]
+            }
+        }
+    }))
+    .unwrap();
+
+    let unresolved_references = Definitions::get_from_definition(definition)
+        .unwrap()
+        .validate();
+
+    assert_eq!(
+        vec![
+            unresolved("edges.workflows", "processor", "#/githb"),
+            unresolved("metadata.build", "edges.#/releases", "#/releases"),
+            unresolved(
+                "metadata.build",
+                "edges.#/releases.arguments",
+                "#/arguments/brnch"
+            ),
+            unresolved("metadata.build", "edges.#/worflows", "#/worflows"),
+            unresolved("metadata.build", "tags.#/tags/rust", "#/tags/rust"),
+            unresolved("processors.github", "arguments", "#/arguments/tokn"),
+        ],
+        unresolved_references
+    );
+}
@@ -0,0 +1,120 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use common::{
+    definition::{self, processed_definition},
+    definitions::{uncached_definitions, uncached_options},
+};
+use definitions::definitions::{Definitions, DefinitionsOptions};
+use engine::{errors::CharaError, processor::ProcessorResult};
+use serde_json::{json, Value};
+
+mod common;
+
+const LOOP: &str = r#"(module (func (export "_start") (loop $forever (br $forever))))"#;
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+fn definition_directory(name: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("chara_wasm_{name}_{}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Runs the wasm processor declared by a definition located in the directory, bounded by `TIMEOUT`.
+fn enrich(directory: &Path, processor: Value) -> Result<ProcessorResult, CharaError> {
+    let mut definition = processed_definition("wasm", processor);
+    definition["location"] = json!(directory.join("chara.json"));
+    let definitions = Definitions::new(DefinitionsOptions {
+        timeout: Some(TIMEOUT),
+        ..uncached_options()
+    });
+    definition::enrich(&definitions, definition)
+}
+
+/// Builds the `scrappers/file` example, requires the `wasm32-wasip1` target.
+fn file_wasm() -> &'static Path {
+    static PROGRAM: OnceLock<PathBuf> = OnceLock::new();
+    PROGRAM.get_or_init(|| {
+        let scrappers = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../scrappers");
+        let status = Command::new(env!("CARGO"))
+            .args([
+                "build",
+                "-p",
+                "file",
+                "--target",
+                "wasm32-wasip1",
+                "--release",
+            ])
+            .arg("--manifest-path")
+            .arg(scrappers.join("Cargo.toml"))
+            .status()
+            .unwrap();
+        assert!(status.success(), "could not build scrappers/file");
+        scrappers.join("target/wasm32-wasip1/release/file.wasm")
+    })
+}
+
+fn read_file(directory: &Path, file: &str) -> Result<ProcessorResult, CharaError> {
+    let mut definition = processed_definition("wasm", json!({ "program": file_wasm() }));
+    definition["metadata"]["build"]["file"] = json!(file);
+    definition["location"] = json!(directory.join("chara.json"));
+    definition::enrich(&uncached_definitions(), definition)
+}
+
+#[test]
+fn should_run_wasm_processor_in_definition_directory() {
+    let directory = definition_directory("read");
+    fs::write(directory.join("build.yaml"), "on: push").unwrap();
+
+    let result = read_file(&directory, "build.yaml").unwrap();
+
+    assert_eq!(
+        "on: push",
+        result.enrichment.unwrap().edge.unwrap()["content"]
+    );
+}
+
+#[test]
+fn should_not_read_outside_definition_directory() {
+    let directory = definition_directory("escape");
+
+    let result = read_file(&directory, "/etc/hostname");
+
+    assert!(matches!(result, Err(CharaError::ProcessExit(_, _))));
+}
+
+#[test]
+fn should_interrupt_wasm_processor_on_timeout() {
+    let directory = definition_directory("loop");
+    fs::write(directory.join("loop.wat"), LOOP).unwrap();
+
+    let result = enrich(&directory, json!({ "program": "./loop.wat" }));
+
+    assert!(matches!(
+        result,
+        Err(CharaError::Timeout(_, timeout)) if timeout == TIMEOUT
+    ));
+}
+
+#[test]
+fn should_retry_wasm_processor() {
+    let directory = definition_directory("retry");
+    fs::write(directory.join("loop.wat"), LOOP).unwrap();
+    let start = Instant::now();
+
+    let result = enrich(
+        &directory,
+        json!({
+            "program": "./loop.wat",
+            "retry": { "attempts": 2 }
+        }),
+    );
+
+    assert!(matches!(result, Err(CharaError::Timeout(_, _))));
+    assert!(start.elapsed() >= 2 * TIMEOUT);
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+use async_trait::async_trait;
+use common::{
+    thread::{readonly, Readonly},
+    ThreadError,
+};
+use log::warn;
+use tokio::{
+    sync::Semaphore,
+    task::{Id, JoinSet},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    contexts::ProcessorContext,
+    definition::{
+        definition::Definition, foreign_definition::ForeignDefinition,
+        input::DefinedDefinitionInput,
+    },
+    errors::CharaError,
+    observer::EngineObserver,
+    options::RunOptions,
+    processor::ProcessorResult,
+    report::{ContextReport, DefinitionIdentity, Outcome, RunReport},
+    state::{ContextRun, ForeignDefinitionLoad, RunState},
+};
+
+#[async_trait]
+pub trait AsyncDefinitions: Send + Sync {
+    async fn get(&self, definition: &DefinedDefinitionInput) -> Result<Definition, CharaError>;
+    async fn enrich(
+        &self,
+        context: &ProcessorContext,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError>;
+    async fn save(&self, definition: &Definition) -> Result<(), CharaError>;
+    async fn save_report(
+        &self,
+        definition: &Definition,
+        report: &RunReport,
+    ) -> Result<(), CharaError>;
+    /// Called once the run is over, successfully or not, to release what it kept running.
+    async fn finish(&self) {}
+}
+
+/// Bounds the tasks spawned by an asynchronous run, the global and per processor counterpart of [`crate::executor::Executor`].
+pub struct AsyncExecutor {
+    permits: Arc<Semaphore>,
+    processor_parallelism: Option<usize>,
+    processor_permits: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl AsyncExecutor {
+    pub fn new(parallelism: Option<usize>, processor_parallelism: Option<usize>) -> Self {
+        let parallelism = parallelism
+            .or(thread::available_parallelism().ok().map(NonZeroUsize::get))
+            .unwrap_or(1)
+            .max(1);
+        Self {
+            permits: Arc::new(Semaphore::new(parallelism)),
+            processor_parallelism,
+            processor_permits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        tasks: &mut JoinSet<T>,
+        processor_identity: Option<&str>,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> Id {
+        let permits = self.permits.clone();
+        let processor_permits =
+            processor_identity.and_then(|identity| self.processor_permits(identity));
+        tasks
+            .spawn(async move {
+                let _processor_permit = match processor_permits {
+                    Some(processor_permits) => processor_permits.acquire_owned().await.ok(),
+                    None => None,
+                };
+                let _permit = permits.acquire_owned().await;
+                future.await
+            })
+            .id()
+    }
+
+    fn processor_permits(&self, processor_identity: &str) -> Option<Arc<Semaphore>> {
+        self.processor_parallelism
+            .and_then(|processor_parallelism| {
+                self.processor_permits.lock().ok().map(|mut permits| {
+                    permits
+                        .entry(processor_identity.to_string())
+                        .or_insert_with(|| Arc::new(Semaphore::new(processor_parallelism.max(1))))
+                        .clone()
+                })
+            })
+    }
+}
+
+struct AsyncRunner<'a> {
+    definitions: Arc<dyn AsyncDefinitions>,
+    executor: AsyncExecutor,
+    state: RunState<'a>,
+}
+
+/// Asynchronous counterpart of [`crate::run`], dropping the returned future aborts the pending processors.
+/// Cancelling `cancellation` drops the running processor futures as well, so their processes are killed.
+pub async fn run(
+    definition: Definition,
+    definitions: Arc<dyn AsyncDefinitions>,
+    options: &RunOptions,
+    observer: Arc<dyn EngineObserver>,
+    cancellation: CancellationToken,
+) -> Result<(Definition, RunReport), CharaError> {
+    let mut runner = AsyncRunner {
+        definitions: definitions.clone(),
+        executor: AsyncExecutor::new(options.parallelism, options.processor_parallelism),
+        state: RunState::new(options, observer.clone(), cancellation.clone()),
+    };
+    let path = [DefinitionIdentity::from(&definition)];
+    let definition = runner
+        .process_definition(readonly(definition.clone()), &path)
+        .await;
+    definitions.finish().await;
+    let definition = definition?;
+    if cancellation.is_cancelled() {
+        warn!("Run of definition {} cancelled", definition.id);
+        runner.state.report.cancelled = true;
+    }
+    definitions.save(&definition).await?;
+    observer.definition_saved(&definition);
+    definitions
+        .save_report(&definition, &runner.state.report)
+        .await?;
+    Ok((definition, runner.state.report))
+}
+
+impl AsyncRunner<'_> {
+    async fn process_definition(
+        &mut self,
+        definition: Readonly<Definition>,
+        path: &[DefinitionIdentity],
+    ) -> Result<Definition, CharaError> {
+        let definition_value = definition
+            .read()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+            .clone();
+        self.state.observer.definition_loaded(&definition_value);
+        let results = self.get_definitions(&definition_value).await;
+        RunState::merge_foreign_definitions(results)?;
+        definition_value.link()?;
+        let contexts = self.state.contexts(&definition_value);
+        let results = self.enrich(contexts, &definition, &definition_value).await;
+
+        let mut children = vec![];
+        for (context, result) in results {
+            if let Some(child) = self
+                .state
+                .merge_result(&definition, context, result, path)?
+            {
+                children.push(child);
+            }
+        }
+        definition_value.link()?;
+        for child in children {
+            let processed =
+                Box::pin(self.process_definition(readonly(child.definition.clone()), &child.path))
+                    .await?;
+            child.store(processed)?;
+        }
+        Ok(definition_value)
+    }
+
+    async fn get_definitions(
+        &mut self,
+        definition: &Definition,
+    ) -> Vec<(Readonly<ForeignDefinition>, Option<Definition>)> {
+        let mut tasks = JoinSet::new();
+        let mut reports = HashMap::new();
+        for foreign_input in self.state.foreign_inputs(definition) {
+            let report = foreign_input.report(&definition.id);
+            let definitions = self.definitions.clone();
+            let processor_identity = foreign_input.processor_identity();
+            let cancellation = self.state.cancellation.clone();
+            let id = self
+                .executor
+                .spawn(&mut tasks, processor_identity.as_deref(), async move {
+                    let start = Instant::now();
+                    let result = match foreign_input.input {
+                        Some(input) => tokio::select! {
+                            biased;
+                            _ = cancellation.cancelled() => Err(CharaError::Cancelled),
+                            result = definitions.get(&input) => result.map(Some),
+                        },
+                        None => Ok(None),
+                    };
+                    ForeignDefinitionLoad {
+                        definition: foreign_input.definition,
+                        result,
+                        duration: start.elapsed(),
+                    }
+                });
+            reports.insert(id, report);
+        }
+        join_all(tasks, reports)
+            .await
+            .into_iter()
+            .filter_map(|(report, load)| self.state.foreign_definition_loaded(report, load))
+            .collect()
+    }
+
+    async fn enrich(
+        &mut self,
+        contexts: Vec<ProcessorContext>,
+        parent: &Readonly<Definition>,
+        definition: &Definition,
+    ) -> Vec<(ProcessorContext, ProcessorResult)> {
+        let mut tasks = JoinSet::new();
+        let mut reports = HashMap::new();
+        for context in contexts {
+            let report =
+                ContextReport::new(&definition.id, &context.definition, Outcome::skipped());
+            let definitions = self.definitions.clone();
+            let parent = parent.clone();
+            let processor_identity = context.processor.identity();
+            let observer = self.state.observer.clone();
+            let cancellation = self.state.cancellation.clone();
+            let definition_id = definition.id.clone();
+            observer.context_scheduled(&definition_id, &context.definition);
+            let id = self
+                .executor
+                .spawn(&mut tasks, Some(&processor_identity), async move {
+                    let start = Instant::now();
+                    let result = if cancellation.is_cancelled() {
+                        Err(CharaError::Cancelled)
+                    } else {
+                        observer.context_started(&definition_id, &context.definition);
+                        tokio::select! {
+                            biased;
+                            _ = cancellation.cancelled() => Err(CharaError::Cancelled),
+                            result = definitions.enrich(&context, parent) => result,
+                        }
+                    };
+                    ContextRun {
+                        context,
+                        result,
+                        duration: start.elapsed(),
+                    }
+                });
+            reports.insert(id, report);
+        }
+        join_all(tasks, reports)
+            .await
+            .into_iter()
+            .filter_map(|(report, run)| self.state.context_ran(report, run))
+            .collect()
+    }
+}
+
+/// Pairs the output of every task with the report registered under its id, a task which panicked yields a join error.
+async fn join_all<T: Send + 'static, R>(
+    mut tasks: JoinSet<T>,
+    mut reports: HashMap<Id, R>,
+) -> Vec<(R, Result<T, CharaError>)> {
+    let mut results = vec![];
+    while let Some(result) = tasks.join_next_with_id().await {
+        let (id, result) = match result {
+            Ok((id, output)) => (id, Ok(output)),
+            Err(err) => (err.id(), Err(CharaError::Thread(ThreadError::Join))),
+        };
+        if let Some(report) = reports.remove(&id) {
+            results.push((report, result));
+        }
+    }
+    results
+}
@@ -36,7 +36,7 @@ pub(crate) struct EdgeContext {
     pub processor: DefinedProcessorOverride,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct WritePermissionsDto {
     pub metadata: bool,
@@ -63,7 +63,7 @@ impl WritePermissionsDto {
         }
     }
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct ContextDto {
     pub name: String,
@@ -76,7 +76,7 @@ impl ContextDto {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct DefinitionContextDto {
     pub location: Option<String>,
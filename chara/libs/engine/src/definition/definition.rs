@@ -13,6 +13,7 @@ use crate::{
         edge::Edge, foreign_definition::ForeignDefinition, metadata::Metadata, tag::RefTag,
     },
     processor::Processor,
+    report::DefinitionIdentity,
 };
 
 #[derive(Debug, Clone)]
@@ -28,7 +29,6 @@ pub struct Definition {
     pub arguments: HashMap<String, Readonly<Vec<String>>>,
     pub environments: HashMap<String, Readonly<HashMap<String, String>>>,
     pub foreign_definitions: HashMap<String, Readonly<ForeignDefinition>>,
-
 }
 
 impl Merge for Definition {
@@ -67,10 +67,24 @@ impl Definition {
             arguments,
             environments,
             foreign_definitions: HashMap::new(),
-
         }
     }
 
+    pub fn reference(identity: &DefinitionIdentity) -> Self {
+        Self::new(
+            None,
+            identity.name.clone(),
+            identity.id.clone(),
+            identity.location.clone(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+    }
+
+    /// Contexts of the processors declared by metadata and their edges.
+    /// The metadata edge inputs are layered on the edge processor with [`crate::processor::ProcessorOverride::from_with`], so a command receives
+    /// the processor arguments, then the edge processor override arguments, then the metadata edge arguments.
+    /// Environments are collected in the same order, a later variable overriding an earlier one, so the metadata edge wins.
     pub fn processors_contexts(&self) -> Vec<ProcessorContext> {
         let definition_contexts = self.metadata.iter().map(|(metadata_key, metadata_value)| {
             metadata_value.read().ok().map(|metadata_lock| {
@@ -85,7 +99,10 @@ impl Definition {
                                 .map(|processor| EdgeContext {
                                     key: edge_key.clone(),
                                     value: edge.other.clone(),
-                                    processor: processor.clone(),
+                                    processor: processor.from_with(
+                                        edge_value.arguments.clone(),
+                                        edge_value.environments.clone(),
+                                    ),
                                 })
                         })
                     })
@@ -114,6 +131,11 @@ impl Definition {
                             .clone()
                             .and_then(|processor| processor.map())
                         {
+                            let edge_override = &metadata_lock.edges[&edge_context.key];
+                            let processor = processor.from_with(
+                                edge_override.arguments.clone(),
+                                edge_override.environments.clone(),
+                            );
                             if processor == edge_context.processor {
                                 ProcessorContext {
                                     definition: DefinitionContextDto {
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use common::thread::Readonly;
 
-use crate::{definition, processor::Processor};
+use crate::processor::Processor;
 
 use super::{definition::Definition, edge::Edge, metadata::Metadata, tag::RefTag};
 
@@ -18,7 +18,6 @@ impl Definition {
         &self,
         segments: &[&str],
     ) -> Option<(Option<String>, Definition)> {
-        dbg!(&segments);
         match &segments[..] {
             [] => Some((None, self.clone())),
             ["#"] => Some((None, self.find_root())),
@@ -48,6 +47,17 @@ impl Definition {
                             edge.definition
                                 .as_ref()
                                 .and_then(|definition| definition.find_definition_by_segments(tail))
+                                .or_else(|| {
+                                    edge.edge
+                                        .value()
+                                        .and_then(|edge| edge.definition)
+                                        .and_then(|foreign_definition| {
+                                            foreign_definition.read().ok()?.output.clone()
+                                        })
+                                        .and_then(|definition| {
+                                            definition.find_definition_by_segments(tail)
+                                        })
+                                })
                         })
                 }),
         }
@@ -57,6 +67,9 @@ impl Definition {
             .split("/")
             .filter(|segment| !segment.is_empty())
             .collect::<Vec<_>>();
+        if segments.is_empty() {
+            return None;
+        }
         self.find_definition_by_segments(&segments)
     }
     pub fn find_processor(&self, path: &String) -> Option<Readonly<Processor>> {
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use common::merge::{Merge, Overwrite};
 
 use crate::cli::{DraftArguments, DraftEnvironments};
@@ -9,6 +11,11 @@ pub struct Install {
     pub program: String,
     pub environments: Vec<DraftEnvironments>,
     pub current_directory: Option<String>,
+    pub timeout: Option<Duration>,
+    /// Arguments given to the processor program, the installation is skipped when it succeeds.
+    pub check: Option<Vec<String>>,
+    /// Location of the definition declaring it, relative paths are resolved from its directory.
+    pub location: Option<String>,
 }
 impl Merge for Install {
     fn merge(&mut self, other: &Self) {
@@ -16,5 +23,8 @@ impl Merge for Install {
         self.program = other.program.clone();
         self.environments.merge(&other.environments);
         self.current_directory.overwrite(&other.current_directory);
+        self.timeout.overwrite(&other.timeout);
+        self.check.overwrite(&other.check);
+        self.location.overwrite(&other.location);
     }
 }
@@ -0,0 +1,147 @@
+use common::{thread::Readonly, ThreadError};
+use log::info;
+
+use crate::{
+    definition::{definition::Definition, input::DraftDefinitionInput},
+    errors::CharaError,
+    processor::DraftProcessorOverride,
+    reference_value::{LazyRef, LazyRefOrValue, ReferencedValue},
+};
+
+fn link_value<T>(
+    value: &mut LazyRefOrValue<T>,
+    find: impl Fn(&String) -> Option<Readonly<T>>,
+) -> usize {
+    let LazyRefOrValue::Ref(reference) = value else {
+        return 0;
+    };
+    let Some(found) = find(reference) else {
+        return 0;
+    };
+    *value = LazyRefOrValue::ReferencedValue(ReferencedValue {
+        r#ref: reference.clone(),
+        value: found,
+    });
+    1
+}
+
+impl Definition {
+    fn link_processor_override(&self, processor: &mut DraftProcessorOverride) -> usize {
+        let mut linked = 0;
+        if let Some(LazyRef::Ref(reference)) = processor.processor.as_ref() {
+            if let Some(found) = self.find_processor(reference) {
+                processor.processor = Some(LazyRef::new_referenced_value(reference.clone(), found));
+                linked += 1;
+            }
+        }
+        for arguments in processor.arguments.iter_mut() {
+            linked += link_value(arguments, |path| self.find_argument(path));
+        }
+        for environments in processor.environments.iter_mut() {
+            linked += link_value(environments, |path| self.find_environment(path));
+        }
+        linked
+    }
+
+    /// Upgrades the references left unresolved by the mappers whose target now exists, such as a processor shipped by an imported definition.
+    /// Values are linked on a copy and written back so lookups never wait on a lock held by the pass itself.
+    pub fn link(&self) -> Result<usize, CharaError> {
+        let mut linked = 0;
+        for metadata in self.metadata.values() {
+            let mut value = metadata
+                .read()
+                .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                .clone();
+            let mut metadata_linked = 0;
+            for edge in value.edges.values_mut() {
+                metadata_linked += link_value(&mut edge.edge, |path| self.find_edge(path));
+                for arguments in edge.arguments.iter_mut() {
+                    metadata_linked += link_value(arguments, |path| self.find_argument(path));
+                }
+                for environments in edge.environments.iter_mut() {
+                    metadata_linked += link_value(environments, |path| self.find_environment(path));
+                }
+            }
+            for tag in value.tags.values_mut() {
+                metadata_linked += link_value(tag, |path| self.find_tag(path));
+            }
+            if let Some(processor) = value.processor.as_mut() {
+                metadata_linked += self.link_processor_override(processor);
+            }
+            if metadata_linked > 0 {
+                *metadata
+                    .write()
+                    .map_err(|_| CharaError::Thread(ThreadError::Poison))? = value;
+                linked += metadata_linked;
+            }
+        }
+        for edge in self.edges.values() {
+            let mut value = edge
+                .read()
+                .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                .processor
+                .clone();
+            let edge_linked = value
+                .as_mut()
+                .map_or(0, |processor| self.link_processor_override(processor));
+            if edge_linked > 0 {
+                edge.write()
+                    .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                    .processor = value;
+                linked += edge_linked;
+            }
+        }
+        for processor in self.processors.values() {
+            let mut value = processor
+                .read()
+                .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                .clone();
+            let mut processor_linked = 0;
+            for arguments in value.arguments.iter_mut() {
+                processor_linked += link_value(arguments, |path| self.find_argument(path));
+            }
+            for environments in value.environments.iter_mut() {
+                processor_linked += link_value(environments, |path| self.find_environment(path));
+            }
+            if let Some(install) = value.install.as_mut() {
+                for arguments in install.arguments.iter_mut() {
+                    processor_linked += link_value(arguments, |path| self.find_argument(path));
+                }
+                for environments in install.environments.iter_mut() {
+                    processor_linked +=
+                        link_value(environments, |path| self.find_environment(path));
+                }
+            }
+            if processor_linked > 0 {
+                *processor
+                    .write()
+                    .map_err(|_| CharaError::Thread(ThreadError::Poison))? = value;
+                linked += processor_linked;
+            }
+        }
+        for foreign_definition in self.foreign_definitions.values() {
+            let mut input = foreign_definition
+                .read()
+                .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                .input
+                .clone();
+            let input_linked = match input.as_mut() {
+                Some(DraftDefinitionInput::Processor(processor)) => {
+                    self.link_processor_override(processor)
+                }
+                _ => 0,
+            };
+            if input_linked > 0 {
+                foreign_definition
+                    .write()
+                    .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+                    .input = input;
+                linked += input_linked;
+            }
+        }
+        if linked > 0 {
+            info!("Linked {linked} references of definition {}", self.id);
+        }
+        Ok(linked)
+    }
+}
@@ -5,4 +5,6 @@ pub mod foreign_definition;
 pub mod edge;
 pub mod metadata;
 pub mod install;
-pub mod tag;
\ No newline at end of file
+pub mod link;
+pub mod tag;
+pub mod validation;
\ No newline at end of file
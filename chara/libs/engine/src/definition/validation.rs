@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    definition::{definition::Definition, input::DraftDefinitionInput},
+    processor::DraftProcessorOverride,
+    reference_value::{LazyRef, LazyRefOrValue},
+};
+
+/// Reference left unresolved by the mappers, such as an edge pointing to a misspelled path.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct UnresolvedReference {
+    /// Entry declaring the reference, e.g. `metadata.repository` or `processors.github`.
+    pub key: String,
+    pub field: String,
+    /// Path that was looked for, e.g. `#/edges/worflows`.
+    pub reference: String,
+}
+
+#[derive(Default)]
+struct Validation {
+    unresolved: Vec<UnresolvedReference>,
+}
+
+impl Validation {
+    fn push(&mut self, key: &str, field: &str, reference: &str) {
+        self.unresolved.push(UnresolvedReference {
+            key: key.to_string(),
+            field: field.to_string(),
+            reference: reference.to_string(),
+        });
+    }
+
+    fn lazy_ref_or_values<T>(&mut self, key: &str, field: &str, values: &[LazyRefOrValue<T>]) {
+        for value in values {
+            if let LazyRefOrValue::Ref(reference) = value {
+                self.push(key, field, reference);
+            }
+        }
+    }
+
+    fn processor_override(&mut self, key: &str, field: &str, processor: &DraftProcessorOverride) {
+        if let Some(LazyRef::Ref(reference)) = processor.processor.as_ref() {
+            self.push(key, field, reference);
+        }
+        self.lazy_ref_or_values(key, &format!("{field}.arguments"), &processor.arguments);
+        self.lazy_ref_or_values(
+            key,
+            &format!("{field}.environments"),
+            &processor.environments,
+        );
+    }
+
+    fn input(&mut self, key: &str, input: &DraftDefinitionInput) {
+        if let DraftDefinitionInput::Processor(processor) = input {
+            self.processor_override(key, "input", processor);
+        }
+    }
+}
+
+impl Definition {
+    /// Lists the references which could not be resolved, the definition is valid when none is returned.
+    pub fn validate(&self) -> Vec<UnresolvedReference> {
+        let mut validation = Validation::default();
+        for (name, metadata) in &self.metadata {
+            let Ok(metadata) = metadata.read() else {
+                continue;
+            };
+            let key = format!("metadata.{name}");
+            for (edge_name, edge) in &metadata.edges {
+                let field = format!("edges.{edge_name}");
+                validation.lazy_ref_or_values(&key, &field, std::slice::from_ref(&edge.edge));
+                validation.lazy_ref_or_values(&key, &format!("{field}.arguments"), &edge.arguments);
+                validation.lazy_ref_or_values(
+                    &key,
+                    &format!("{field}.environments"),
+                    &edge.environments,
+                );
+            }
+            for (tag_key, tag) in &metadata.tags {
+                validation.lazy_ref_or_values(
+                    &key,
+                    &format!("tags.{tag_key}"),
+                    std::slice::from_ref(tag),
+                );
+            }
+            if let Some(processor) = metadata.processor.as_ref() {
+                validation.processor_override(&key, "processor", processor);
+            }
+        }
+        for (name, edge) in &self.edges {
+            let Ok(edge) = edge.read() else {
+                continue;
+            };
+            if let Some(processor) = edge.processor.as_ref() {
+                validation.processor_override(&format!("edges.{name}"), "processor", processor);
+            }
+        }
+        for (name, processor) in &self.processors {
+            let Ok(processor) = processor.read() else {
+                continue;
+            };
+            let key = format!("processors.{name}");
+            validation.lazy_ref_or_values(&key, "arguments", &processor.arguments);
+            validation.lazy_ref_or_values(&key, "environments", &processor.environments);
+            if let Some(install) = processor.install.as_ref() {
+                validation.lazy_ref_or_values(&key, "install.arguments", &install.arguments);
+                validation.lazy_ref_or_values(&key, "install.environments", &install.environments);
+            }
+        }
+        for (name, foreign_definition) in &self.foreign_definitions {
+            if let Some(input) = foreign_definition
+                .read()
+                .ok()
+                .and_then(|foreign_definition| foreign_definition.input.clone())
+            {
+                validation.input(&format!("definitions.{name}"), &input);
+            }
+        }
+        validation.unresolved.sort();
+        validation.unresolved
+    }
+}
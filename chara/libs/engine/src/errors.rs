@@ -1,7 +1,7 @@
-use std::string::FromUtf8Error;
+use std::{string::FromUtf8Error, time::Duration};
 
-use thiserror::Error;
 use common::ThreadError;
+use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum CharaError {
@@ -13,6 +13,10 @@ pub enum CharaError {
     IO(std::io::Error),
     #[error("Process error {0}")]
     Process(String),
+    #[error("Process exited with code {0:?} [Error : {1}]")]
+    ProcessExit(Option<i32>, String),
+    #[error("Process {0} killed after {1:?}")]
+    Timeout(String, Duration),
     #[error("Thread error {0}")]
     Thread(ThreadError),
     #[error("Cli error {0}")]
@@ -21,4 +25,29 @@ pub enum CharaError {
     InvalidPath(String),
     #[error("Path parsing failed")]
     ParsePath,
+    #[error("Run cancelled")]
+    Cancelled,
+    #[error("Manifest of processor {0} is invalid {1}")]
+    InvalidManifest(String, String),
+    #[error("Context rejected by processor {0} [Error : {1}]")]
+    InvalidContext(String, String),
+    #[error("Output of processor {0} is invalid [Error : {}]", .1.join(", "))]
+    InvalidOutput(String, Vec<String>),
+    #[error("Secret {0} unavailable {1}")]
+    Secret(String, String),
+    #[error("Placeholder {0} unresolved in {1}")]
+    Placeholder(String, String),
+    #[error("Processor plugin {0} is not registered")]
+    UnknownPlugin(String),
+    #[error("Processor plugin {0} cannot {1}")]
+    UnsupportedPlugin(String, String),
+}
+
+impl CharaError {
+    pub fn stderr(&self) -> Option<&str> {
+        match self {
+            CharaError::ProcessExit(_, stderr) => Some(stderr),
+            _ => None,
+        }
+    }
 }
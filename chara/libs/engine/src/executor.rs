@@ -0,0 +1,185 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use common::ThreadError;
+use log::error;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed size pool of worker threads shared by a whole run.
+pub struct Executor {
+    dispatcher: Arc<Mutex<Dispatcher>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct Dispatcher {
+    sender: Option<Sender<Job>>,
+    processor_parallelism: Option<usize>,
+    processor_queues: HashMap<String, ProcessorQueue>,
+}
+
+#[derive(Default)]
+struct ProcessorQueue {
+    running: usize,
+    pending: VecDeque<Job>,
+}
+
+impl Dispatcher {
+    fn send(&self, job: Job) {
+        if let Some(jobs) = self.sender.as_ref() {
+            let _ = jobs.send(job);
+        }
+    }
+}
+
+/// Gives the slot of its processor to the next pending job once dropped, the job panicking included.
+struct ProcessorSlot {
+    dispatcher: Arc<Mutex<Dispatcher>>,
+    processor_identity: String,
+}
+
+impl Drop for ProcessorSlot {
+    fn drop(&mut self) {
+        let Ok(mut dispatcher) = self.dispatcher.lock() else {
+            return;
+        };
+        let Some(queue) = dispatcher
+            .processor_queues
+            .get_mut(&self.processor_identity)
+        else {
+            return;
+        };
+        match queue.pending.pop_front() {
+            Some(job) => {
+                let job = slotted(
+                    self.dispatcher.clone(),
+                    self.processor_identity.clone(),
+                    job,
+                );
+                dispatcher.send(job);
+            }
+            None => queue.running -= 1,
+        }
+    }
+}
+
+fn slotted(dispatcher: Arc<Mutex<Dispatcher>>, processor_identity: String, job: Job) -> Job {
+    Box::new(move || {
+        let _slot = ProcessorSlot {
+            dispatcher,
+            processor_identity,
+        };
+        job()
+    })
+}
+
+pub struct Task<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Task<T> {
+    pub fn join(self) -> Result<T, ThreadError> {
+        self.receiver.recv().map_err(|_| ThreadError::Join)
+    }
+}
+
+impl Executor {
+    pub fn new(parallelism: Option<usize>, processor_parallelism: Option<usize>) -> Self {
+        let parallelism = parallelism
+            .or(thread::available_parallelism().ok().map(NonZeroUsize::get))
+            .unwrap_or(1)
+            .max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..parallelism)
+            .map(|_| {
+                let receiver = receiver.clone();
+                thread::spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    match job {
+                        Ok(job) => {
+                            if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                error!("Executor job panicked");
+                            }
+                        }
+                        Err(_) => break,
+                    }
+                })
+            })
+            .collect();
+        Self {
+            dispatcher: Arc::new(Mutex::new(Dispatcher {
+                sender: Some(sender),
+                processor_parallelism,
+                processor_queues: HashMap::new(),
+            })),
+            workers,
+        }
+    }
+
+    fn task<T: Send + 'static>(job: impl FnOnce() -> T + Send + 'static) -> (Job, Task<T>) {
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            let _ = sender.send(job());
+        });
+        (job, Task { receiver })
+    }
+
+    pub fn spawn<T: Send + 'static>(&self, job: impl FnOnce() -> T + Send + 'static) -> Task<T> {
+        let (job, task) = Executor::task(job);
+        if let Ok(dispatcher) = self.dispatcher.lock() {
+            dispatcher.send(job);
+        }
+        task
+    }
+
+    /// Queues the job until its processor runs less than `processor_parallelism` jobs, without holding a worker.
+    pub fn spawn_processor<T: Send + 'static>(
+        &self,
+        processor_identity: &str,
+        job: impl FnOnce() -> T + Send + 'static,
+    ) -> Task<T> {
+        let (job, task) = Executor::task(job);
+        if let Ok(mut dispatcher) = self.dispatcher.lock() {
+            let Some(processor_parallelism) = dispatcher.processor_parallelism else {
+                dispatcher.send(job);
+                return task;
+            };
+            let queue = dispatcher
+                .processor_queues
+                .entry(processor_identity.to_string())
+                .or_default();
+            if queue.running < processor_parallelism.max(1) {
+                queue.running += 1;
+                let job = slotted(self.dispatcher.clone(), processor_identity.to_string(), job);
+                dispatcher.send(job);
+            } else {
+                queue.pending.push_back(job);
+            }
+        }
+        task
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        if let Ok(mut dispatcher) = self.dispatcher.lock() {
+            dispatcher.sender.take();
+            dispatcher.processor_queues.clear();
+        }
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
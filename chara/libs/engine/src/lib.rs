@@ -1,173 +1,203 @@
-use std::{
-    sync::Arc,
-    thread::{self},
-};
+use std::{sync::Arc, time::Instant};
 
 use common::{
-    merge::Merge,
     thread::{readonly, Readonly},
     ThreadError,
 };
 use contexts::ProcessorContext;
 
-use definition::{foreign_definition::ForeignDefinition, input::DefinedDefinitionInput};
 use definition::definition::Definition;
+use definition::{foreign_definition::ForeignDefinition, input::DefinedDefinitionInput};
 use errors::CharaError;
-use log::error;
+use executor::Executor;
+use log::warn;
+use observer::EngineObserver;
+use options::RunOptions;
 use processor::ProcessorResult;
+use report::{ContextReport, DefinitionIdentity, Outcome, RunReport};
+use state::{ContextRun, ForeignDefinitionLoad, RunState};
+use tokio_util::sync::CancellationToken;
+pub mod asynchronous;
 pub mod cli;
 pub mod contexts;
 pub mod definition;
 pub mod errors;
+pub mod executor;
+pub mod observer;
+pub mod options;
 pub mod processor;
 pub mod reference_value;
+pub mod report;
+mod state;
 pub trait Definitions: Send + Sync {
     fn get(&self, definition: &DefinedDefinitionInput) -> Result<Definition, CharaError>;
-    fn enrich(&self, context: &ProcessorContext, parent: Readonly<Definition>) -> Result<ProcessorResult, CharaError>;
+    fn enrich(
+        &self,
+        context: &ProcessorContext,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError>;
     fn save(&self, definition: &Definition) -> Result<(), CharaError>;
+    fn save_report(&self, definition: &Definition, report: &RunReport) -> Result<(), CharaError>;
+    /// Called once the run is over, successfully or not, to release what it kept running.
+    fn finish(&self) {}
 }
 
+struct Runner<'a> {
+    definitions: Arc<dyn Definitions>,
+    executor: Executor,
+    state: RunState<'a>,
+}
+
+/// Processes the definition and its edge definitions, then saves the result and its report.
+/// Once `cancellation` is cancelled no context is scheduled anymore and the pending ones are reported as cancelled,
+/// the processes already running are only killed by `definitions` implementations watching the same token.
 pub fn run(
     definition: Definition,
     definitions: Arc<dyn Definitions>,
-) -> Result<Definition, CharaError> {
-    let definition = process_definition(readonly(definition.clone()), &definitions)?;
+    options: &RunOptions,
+    observer: Arc<dyn EngineObserver>,
+    cancellation: CancellationToken,
+) -> Result<(Definition, RunReport), CharaError> {
+    let mut runner = Runner {
+        definitions: definitions.clone(),
+        executor: Executor::new(options.parallelism, options.processor_parallelism),
+        state: RunState::new(options, observer.clone(), cancellation.clone()),
+    };
+    let path = [DefinitionIdentity::from(&definition)];
+    let definition = runner.process_definition(readonly(definition.clone()), &path);
+    definitions.finish();
+    let definition = definition?;
+    if cancellation.is_cancelled() {
+        warn!("Run of definition {} cancelled", definition.id);
+        runner.state.report.cancelled = true;
+    }
     definitions.save(&definition)?;
-    Ok(definition)
+    observer.definition_saved(&definition);
+    definitions.save_report(&definition, &runner.state.report)?;
+    Ok((definition, runner.state.report))
 }
 
-fn process_definition(
-    definition: Readonly<Definition>,
-    definitions: &Arc<dyn Definitions>,
-) -> Result<Definition, CharaError> {
-    let definition_value = definition
-        .read()
-        .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
-    let results = get_definitions(&definition_value, definitions);
-    for (foreign_definition, definition_output) in results {
-        let mut foreign_definition = foreign_definition
-            .write()
-            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
-        if let None = foreign_definition.output {
-            foreign_definition.output.merge(&definition_output);
-        }
-    }
-    let contexts = definition_value.processors_contexts();
-    let results = enrich(contexts, definitions.clone(), &definition);
-
-    handle_results(definition.clone(), results, definitions)?;
-    Ok(definition_value.to_owned())
-}
+impl Runner<'_> {
+    fn process_definition(
+        &mut self,
+        definition: Readonly<Definition>,
+        path: &[DefinitionIdentity],
+    ) -> Result<Definition, CharaError> {
+        let definition_value = definition
+            .read()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?
+            .clone();
+        self.state.observer.definition_loaded(&definition_value);
+        let results = self.get_definitions(&definition_value);
+        RunState::merge_foreign_definitions(results)?;
+        definition_value.link()?;
+        let contexts = self.state.contexts(&definition_value);
+        let results = self.enrich(contexts, &definition, &definition_value);
 
-fn handle_results(
-    source_definition: Readonly<Definition>,
-    results: Vec<(ProcessorContext, ProcessorResult)>,
-    definitions: &Arc<dyn Definitions>,
-) -> Result<(), CharaError> {
-    for (context, result) in results {
-        let mut metadata = context
-            .metadata
-            .write()
-            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
-        if let Some(enrichment) = result.enrichment {
-            if let (true, Some(mut edge_enrichment), Some(edge_context)) = (
-                context.definition.write.edge,
-                enrichment.edge,
-                &context.definition.edge,
-            ) {
-                if let Some(edge) = metadata.edges.get_mut(&edge_context.name) {
-                    edge.other.append(&mut edge_enrichment);
-                }
-            }
-            if let (true, Some(mut metadata_enrichment)) =
-                (context.definition.write.metadata, enrichment.metadata)
+        let mut children = vec![];
+        for (context, result) in results {
+            if let Some(child) = self
+                .state
+                .merge_result(&definition, context, result, path)?
             {
-                metadata.other.append(&mut metadata_enrichment);
+                children.push(child);
             }
         }
-        if let (Some(mut result_definition), Some(edge_context)) =
-            (result.definition, context.definition.edge)
-        {
-            if let Some(edge) = metadata.edges.get_mut(&edge_context.name) {
-                if let Some(src_edge) = edge.edge.value() {
-                    if let Some(foreign_definition) = src_edge.definition.as_ref() {
-                        if let Ok(foreign_definition) = foreign_definition.read() {
-                            if let Some(foreign_definition) = foreign_definition.output.as_ref() {
-                                result_definition.merge(foreign_definition);
-                            }
-                        }
-                    }
-                }
-                edge.definition.merge(&Some(result_definition));
-                if let Some(definition) = edge.definition.as_mut() {
-                    definition.parent = Some(source_definition.clone());
-                    *definition = process_definition(readonly(definition.clone()), definitions)?;
-                }
-            }
+        definition_value.link()?;
+        for child in children {
+            let processed =
+                self.process_definition(readonly(child.definition.clone()), &child.path)?;
+            child.store(processed)?;
         }
+        Ok(definition_value)
     }
-    Ok(())
-}
 
-fn get_definitions(
-    definition: &Definition,
-    definitions: &Arc<dyn Definitions>,
-) -> Vec<(Readonly<ForeignDefinition>, Option<Definition>)> {
-    definition
-        .foreign_definitions
-        .iter()
-        .map(|definition| {
-            let definition = definition.1.clone();
-            let definitions: Arc<dyn Definitions> = definitions.clone();
-            thread::spawn(move || {
-                definition
-                    .read()
-                    .map_err(|_| CharaError::Thread(ThreadError::Poison))
-                    .and_then(|definition| {
-                        definition
+    fn get_definitions(
+        &mut self,
+        definition: &Definition,
+    ) -> Vec<(Readonly<ForeignDefinition>, Option<Definition>)> {
+        self.state
+            .foreign_inputs(definition)
+            .into_iter()
+            .map(|foreign_input| {
+                let report = foreign_input.report(&definition.id);
+                let definitions = self.definitions.clone();
+                let processor_identity = foreign_input.processor_identity();
+                let cancellation = self.state.cancellation.clone();
+                let job = move || {
+                    let start = Instant::now();
+                    let result = if cancellation.is_cancelled() {
+                        Err(CharaError::Cancelled)
+                    } else {
+                        foreign_input
                             .input
-                            .as_ref()
-                            .and_then(|input| input.to_defined())
                             .map(|input| definitions.get(&input))
                             .transpose()
-                    })
-                    .map(|found_definition| (definition, found_definition))
+                    };
+                    ForeignDefinitionLoad {
+                        definition: foreign_input.definition,
+                        result,
+                        duration: start.elapsed(),
+                    }
+                };
+                let task = match processor_identity {
+                    Some(processor_identity) => {
+                        self.executor.spawn_processor(&processor_identity, job)
+                    }
+                    None => self.executor.spawn(job),
+                };
+                (report, task)
             })
-        })
-        .map(|handler| {
-            handler
-                .join()
-                .map_err(|_err| CharaError::Thread(ThreadError::Join))
-                .and_then(|res| res)
-                .inspect_err(|err| error!("get_definitions {err}"))
-        })
-        .flatten()
-        .collect()
-}
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|(report, task)| {
+                self.state
+                    .foreign_definition_loaded(report, task.join().map_err(CharaError::Thread))
+            })
+            .collect()
+    }
 
-fn enrich(
-    contexts: Vec<ProcessorContext>,
-    definitions: Arc<dyn Definitions>,
-    parent: &Readonly<Definition>
-) -> Vec<(ProcessorContext, ProcessorResult)> {
-    contexts
-        .into_iter()
-        .map(|context| {
-            let definitions = definitions.clone();
-            let parent = parent.clone();
-            thread::spawn(move || {
-                definitions
-                    .enrich(&context, parent)
-                    .map(|processor_result| (context, processor_result))
+    fn enrich(
+        &mut self,
+        contexts: Vec<ProcessorContext>,
+        parent: &Readonly<Definition>,
+        definition: &Definition,
+    ) -> Vec<(ProcessorContext, ProcessorResult)> {
+        contexts
+            .into_iter()
+            .map(|context| {
+                let report =
+                    ContextReport::new(&definition.id, &context.definition, Outcome::skipped());
+                let definitions = self.definitions.clone();
+                let parent = parent.clone();
+                let observer = self.state.observer.clone();
+                let cancellation = self.state.cancellation.clone();
+                let definition_id = definition.id.clone();
+                observer.context_scheduled(&definition_id, &context.definition);
+                let task =
+                    self.executor
+                        .spawn_processor(&context.processor.identity(), move || {
+                            let start = Instant::now();
+                            let result = if cancellation.is_cancelled() {
+                                Err(CharaError::Cancelled)
+                            } else {
+                                observer.context_started(&definition_id, &context.definition);
+                                definitions.enrich(&context, parent)
+                            };
+                            ContextRun {
+                                context,
+                                result,
+                                duration: start.elapsed(),
+                            }
+                        });
+                (report, task)
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|(report, task)| {
+                self.state
+                    .context_ran(report, task.join().map_err(CharaError::Thread))
             })
-        })
-        .map(|handler| {
-            handler
-                .join()
-                .map_err(|_err| CharaError::Thread(ThreadError::Join))
-                .and_then(|res| res)
-                .inspect_err(|err| error!("enrich {err}"))
-        })
-        .flatten()
-        .collect()
+            .collect()
+    }
 }
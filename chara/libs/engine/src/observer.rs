@@ -0,0 +1,25 @@
+use crate::{
+    contexts::DefinitionContextDto,
+    definition::definition::Definition,
+    report::{ContextReport, ForeignDefinitionReport},
+};
+
+/// Hooks called by a run at each step of its lifecycle, every method does nothing by default.
+/// Methods may be called concurrently from the executor workers.
+pub trait EngineObserver: Send + Sync {
+    /// A definition is about to be processed, the root first and then every edge definition.
+    fn definition_loaded(&self, _definition: &Definition) {}
+    fn foreign_definition_loaded(&self, _report: &ForeignDefinitionReport) {}
+    fn context_scheduled(&self, _definition: &str, _context: &DefinitionContextDto) {}
+    fn context_started(&self, _definition: &str, _context: &DefinitionContextDto) {}
+    /// The context succeeded, was skipped or was cancelled.
+    fn context_finished(&self, _report: &ContextReport) {}
+    fn context_failed(&self, _report: &ContextReport) {}
+    /// The result of the context was written into the definition.
+    fn result_merged(&self, _definition: &str, _context: &DefinitionContextDto) {}
+    fn definition_saved(&self, _definition: &Definition) {}
+}
+
+pub struct NoopObserver;
+
+impl EngineObserver for NoopObserver {}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunOptions {
+    /// Levels of edge definitions processed below the root, `Some(0)` only runs the root processors.
+    pub max_depth: Option<usize>,
+    /// Definitions processed during the whole run, root included.
+    pub max_nodes: Option<usize>,
+    /// Processor references allowed to run, every processor runs when `None`.
+    pub processors: Option<Vec<String>>,
+    /// Worker threads shared by the run, defaults to the available parallelism.
+    pub parallelism: Option<usize>,
+    /// Concurrent executions of a same processor reference, only bounded by `parallelism` when `None`.
+    pub processor_parallelism: Option<usize>,
+}
+
+impl RunOptions {
+    pub fn allows_processor(&self, processor_reference: &str) -> bool {
+        self.processors.as_ref().is_none_or(|processors| {
+            processors
+                .iter()
+                .any(|processor| processor == processor_reference)
+        })
+    }
+    pub fn allows_depth(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth <= max_depth)
+    }
+    pub fn allows_nodes(&self, nodes: usize) -> bool {
+        self.max_nodes.is_none_or(|max_nodes| nodes <= max_nodes)
+    }
+}
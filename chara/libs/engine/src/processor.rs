@@ -1,8 +1,17 @@
-use common::{merge::Merge, thread::Readonly};
+use std::time::Duration;
+
+use common::{
+    merge::{Merge, Overwrite},
+    thread::Readonly,
+};
 use serde_json::{Map, Value};
 
 use crate::{
-    cli::{DraftArguments, DraftEnvironments}, definition::install::Install, definition::definition::Definition, reference_value::{LazyRef, ReferencedValue}
+    cli::{DraftArguments, DraftEnvironments},
+    definition::definition::Definition,
+    definition::install::Install,
+    errors::CharaError,
+    reference_value::{LazyRef, ReferencedValue},
 };
 
 #[derive(Debug)]
@@ -23,12 +32,78 @@ pub struct Processor {
     pub install: Option<Install>,
     pub environments: Vec<DraftEnvironments>,
     pub current_directory: Option<String>,
+    pub timeout: Option<Duration>,
+    pub retry: Option<RetryPolicy>,
+    pub protocol: Protocol,
+    pub manifest: Option<ManifestSource>,
+    /// Location of the definition declaring it, relative paths are resolved from its directory.
+    pub location: Option<String>,
+    /// Grants the network to a wasm processor.
+    pub network: bool,
 }
 impl Merge for Processor {
     fn merge(&mut self, other: &Self) {
         self.arguments.merge(&other.arguments);
         self.program = other.program.clone();
         self.program = other.program.clone();
+        self.timeout.overwrite(&other.timeout);
+        self.retry.overwrite(&other.retry);
+        self.protocol = other.protocol;
+        self.manifest.overwrite(&other.manifest);
+        self.location.overwrite(&other.location);
+        self.network = other.network;
+    }
+}
+
+/// Where the manifest describing what a processor expects and writes is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestSource {
+    /// Printed on stdout by the processor run with `--manifest`.
+    Command,
+    File(String),
+}
+
+/// Latest version of the stdio protocol.
+pub const STDIO_PROTOCOL_VERSION: u32 = 1;
+
+/// How a processor receives its context and returns its result.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Protocol {
+    /// Context given with `--context`, result written to the file given with `--output`.
+    #[default]
+    Arguments,
+    /// Context written to stdin, result read from stdout, stderr is left to the logs.
+    Stdio { version: u32 },
+    /// Started once per run, answers the `enrich` requests written to its stdin as JSON-RPC lines on stdout.
+    JsonRpc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Executions of the processor, the first one included.
+    pub attempts: u32,
+    /// Delay before the second attempt, doubled on each following attempt.
+    pub backoff: Duration,
+    /// Exit codes worth another attempt, every failing exit code when empty.
+    pub exit_codes: Vec<i32>,
+}
+
+impl RetryPolicy {
+    /// Timeouts are always retryable, other errors such as a missing program are not.
+    pub fn is_retryable(&self, error: &CharaError) -> bool {
+        match error {
+            CharaError::ProcessExit(code, _) => {
+                self.exit_codes.is_empty()
+                    || code.is_some_and(|code| self.exit_codes.contains(&code))
+            }
+            CharaError::Timeout(_, _) => true,
+            _ => false,
+        }
+    }
+
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
     }
 }
 
@@ -61,8 +136,8 @@ impl<TArguments: Clone, TEnvironment: Clone, TProcessor: Clone>
     }
     pub fn from_with(&self, arguments: Vec<TArguments>, environments: Vec<TEnvironment>) -> Self {
         let mut processor = self.clone();
-        processor.arguments = [arguments, processor.arguments].concat();
-        processor.environments = [environments, processor.environments].concat();
+        processor.arguments = [processor.arguments, arguments].concat();
+        processor.environments = [processor.environments, environments].concat();
         processor
     }
 }
@@ -71,3 +146,20 @@ pub type DraftProcessorOverride =
     ProcessorOverride<DraftArguments, DraftEnvironments, Option<LazyRef<Processor>>>;
 pub type DefinedProcessorOverride =
     ProcessorOverride<DraftArguments, DraftEnvironments, ReferencedValue<Readonly<Processor>>>;
+
+impl DefinedProcessorOverride {
+    pub fn identity(&self) -> String {
+        let location = self
+            .processor
+            .value
+            .read()
+            .ok()
+            .and_then(|processor| processor.location.clone());
+        processor_identity(&self.processor.r#ref, location.as_deref())
+    }
+}
+
+/// Processors of distinct definitions may share a reference, their declaring location tells them apart.
+pub fn processor_identity(processor_reference: &str, location: Option<&str>) -> String {
+    format!("{}{processor_reference}", location.unwrap_or_default())
+}
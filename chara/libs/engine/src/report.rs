@@ -0,0 +1,158 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    contexts::DefinitionContextDto, definition::definition::Definition, errors::CharaError,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DefinitionIdentity {
+    pub id: String,
+    pub name: String,
+    pub location: Option<String>,
+}
+
+impl DefinitionIdentity {
+    /// Children returned by a processor share the location of their parent, the name tells them apart.
+    pub fn matches(&self, other: &DefinitionIdentity) -> bool {
+        self.id == other.id
+            || (self.location.is_some()
+                && self.location == other.location
+                && self.name == other.name)
+    }
+}
+
+impl From<&Definition> for DefinitionIdentity {
+    fn from(definition: &Definition) -> Self {
+        Self {
+            id: definition.id.clone(),
+            name: definition.name.clone(),
+            location: definition.location.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cycle {
+    pub metadata: String,
+    pub edge: String,
+    pub path: Vec<DefinitionIdentity>,
+    pub definition: DefinitionIdentity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Succeeded,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outcome {
+    pub status: Status,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+    pub stderr: Option<String>,
+}
+
+impl Outcome {
+    pub fn skipped() -> Self {
+        Self {
+            status: Status::Skipped,
+            duration_ms: 0,
+            error: None,
+            stderr: None,
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        Self {
+            status: Status::Cancelled,
+            ..Self::skipped()
+        }
+    }
+
+    pub fn from_result<T>(result: &Result<T, CharaError>, duration: Duration) -> Self {
+        let duration_ms = duration.as_millis().try_into().unwrap_or(u64::MAX);
+        match result {
+            Ok(_) => Self {
+                status: Status::Succeeded,
+                duration_ms,
+                error: None,
+                stderr: None,
+            },
+            Err(err) => Self {
+                status: match err {
+                    CharaError::Cancelled => Status::Cancelled,
+                    _ => Status::Failed,
+                },
+                duration_ms,
+                error: Some(err.to_string()),
+                stderr: err.stderr().map(|stderr| stderr.to_string()),
+            },
+        }
+    }
+}
+
+/// Execution of a processor on a metadata, and its edge when the context has one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextReport {
+    pub definition: String,
+    pub metadata: String,
+    pub edge: Option<String>,
+    pub processor: String,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+impl ContextReport {
+    pub fn new(definition: &str, context: &DefinitionContextDto, outcome: Outcome) -> Self {
+        Self {
+            definition: definition.to_string(),
+            metadata: context.metadata.name.clone(),
+            edge: context.edge.as_ref().map(|edge| edge.name.clone()),
+            processor: context.processor_reference.clone(),
+            outcome,
+        }
+    }
+}
+
+/// Loading of a foreign definition input, the processor is set when the input is a processor output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForeignDefinitionReport {
+    pub definition: String,
+    pub key: String,
+    pub processor: Option<String>,
+    #[serde(flatten)]
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunReport {
+    /// The run was cancelled, the definition only holds the results merged before.
+    #[serde(default)]
+    pub cancelled: bool,
+    pub cycles: Vec<Cycle>,
+    #[serde(default)]
+    pub contexts: Vec<ContextReport>,
+    #[serde(default)]
+    pub foreign_definitions: Vec<ForeignDefinitionReport>,
+}
+
+impl RunReport {
+    pub fn failures(&self) -> usize {
+        self.contexts
+            .iter()
+            .map(|context| &context.outcome)
+            .chain(
+                self.foreign_definitions
+                    .iter()
+                    .map(|foreign_definition| &foreign_definition.outcome),
+            )
+            .filter(|outcome| outcome.status == Status::Failed)
+            .count()
+    }
+}
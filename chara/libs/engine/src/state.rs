@@ -0,0 +1,344 @@
+use std::{sync::Arc, time::Duration};
+
+use common::{merge::Merge, thread::Readonly, ThreadError};
+use log::{error, info, warn};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    contexts::ProcessorContext,
+    definition::{
+        definition::Definition,
+        foreign_definition::ForeignDefinition,
+        input::{BaseDefinitionInput, DefinedDefinitionInput},
+        metadata::Metadata,
+    },
+    errors::CharaError,
+    observer::EngineObserver,
+    options::RunOptions,
+    processor::ProcessorResult,
+    report::{
+        ContextReport, Cycle, DefinitionIdentity, ForeignDefinitionReport, Outcome, RunReport,
+        Status,
+    },
+};
+
+/// Bookkeeping shared by the blocking and the asynchronous engines.
+pub(crate) struct RunState<'a> {
+    pub options: &'a RunOptions,
+    pub observer: Arc<dyn EngineObserver>,
+    pub cancellation: CancellationToken,
+    pub report: RunReport,
+    pub nodes: usize,
+}
+
+pub(crate) struct ForeignInput {
+    pub key: String,
+    pub definition: Readonly<ForeignDefinition>,
+    pub input: Option<DefinedDefinitionInput>,
+}
+
+impl ForeignInput {
+    pub fn processor_reference(&self) -> Option<String> {
+        if let Some(BaseDefinitionInput::Processor(processor)) = self.input.as_ref() {
+            Some(processor.processor.r#ref.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn processor_identity(&self) -> Option<String> {
+        if let Some(BaseDefinitionInput::Processor(processor)) = self.input.as_ref() {
+            Some(processor.identity())
+        } else {
+            None
+        }
+    }
+
+    pub fn report(&self, definition: &str) -> ForeignDefinitionReport {
+        ForeignDefinitionReport {
+            definition: definition.to_string(),
+            key: self.key.clone(),
+            processor: self.processor_reference(),
+            outcome: Outcome::skipped(),
+        }
+    }
+}
+
+/// Foreign definition loaded by a task, with the time spent loading it.
+pub(crate) struct ForeignDefinitionLoad {
+    pub definition: Readonly<ForeignDefinition>,
+    pub result: Result<Option<Definition>, CharaError>,
+    pub duration: Duration,
+}
+
+/// Processor context run by a task, with the time spent running it.
+pub(crate) struct ContextRun {
+    pub context: ProcessorContext,
+    pub result: Result<ProcessorResult, CharaError>,
+    pub duration: Duration,
+}
+
+/// Edge definition returned by a processor which still has to be processed.
+pub(crate) struct Child {
+    pub metadata: Readonly<Metadata>,
+    pub edge: String,
+    pub definition: Definition,
+    pub path: Vec<DefinitionIdentity>,
+}
+
+impl Child {
+    pub fn store(&self, definition: Definition) -> Result<(), CharaError> {
+        let mut metadata = self
+            .metadata
+            .write()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        if let Some(edge) = metadata.edges.get_mut(&self.edge) {
+            edge.definition = Some(definition);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> RunState<'a> {
+    pub fn new(
+        options: &'a RunOptions,
+        observer: Arc<dyn EngineObserver>,
+        cancellation: CancellationToken,
+    ) -> Self {
+        Self {
+            options,
+            observer,
+            cancellation,
+            report: RunReport::default(),
+            nodes: 1,
+        }
+    }
+
+    pub fn foreign_inputs(&self, definition: &Definition) -> Vec<ForeignInput> {
+        if self.cancellation.is_cancelled() {
+            return vec![];
+        }
+        definition
+            .foreign_definitions
+            .iter()
+            .filter_map(|(key, foreign_definition)| {
+                foreign_definition
+                    .read()
+                    .ok()
+                    .map(|definition| ForeignInput {
+                        key: key.clone(),
+                        definition: foreign_definition.clone(),
+                        input: definition
+                            .input
+                            .as_ref()
+                            .and_then(|input| input.to_defined()),
+                    })
+            })
+            .filter(|input| {
+                input
+                    .processor_reference()
+                    .is_none_or(|reference| self.options.allows_processor(&reference))
+            })
+            .collect()
+    }
+
+    pub fn foreign_definition_loaded(
+        &mut self,
+        mut report: ForeignDefinitionReport,
+        load: Result<ForeignDefinitionLoad, CharaError>,
+    ) -> Option<(Readonly<ForeignDefinition>, Option<Definition>)> {
+        let loaded = match load {
+            Ok(load) => {
+                report.outcome = Outcome::from_result(&load.result, load.duration);
+                load.result.ok().map(|output| (load.definition, output))
+            }
+            Err(err) => {
+                report.outcome = Outcome::from_result::<()>(&Err(err), Duration::ZERO);
+                None
+            }
+        };
+        if let Some(err) = report.outcome.error.as_ref() {
+            error!("get_definitions {} {err}", report.key);
+        }
+        self.observer.foreign_definition_loaded(&report);
+        self.report.foreign_definitions.push(report);
+        loaded
+    }
+
+    pub fn merge_foreign_definitions(
+        results: Vec<(Readonly<ForeignDefinition>, Option<Definition>)>,
+    ) -> Result<(), CharaError> {
+        for (foreign_definition, definition_output) in results {
+            let mut foreign_definition = foreign_definition
+                .write()
+                .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+            if foreign_definition.output.is_none() {
+                foreign_definition.output.merge(&definition_output);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contexts(&mut self, definition: &Definition) -> Vec<ProcessorContext> {
+        if self.cancellation.is_cancelled() {
+            for context in definition.processors_contexts() {
+                let report =
+                    ContextReport::new(&definition.id, &context.definition, Outcome::cancelled());
+                self.observer.context_finished(&report);
+                self.report.contexts.push(report);
+            }
+            return vec![];
+        }
+        definition
+            .processors_contexts()
+            .into_iter()
+            .filter(|context| {
+                let allowed = self
+                    .options
+                    .allows_processor(&context.definition.processor_reference);
+                if !allowed {
+                    info!(
+                        "Skip processor {} on metadata {}",
+                        context.definition.processor_reference, context.definition.metadata.name
+                    );
+                    let report =
+                        ContextReport::new(&definition.id, &context.definition, Outcome::skipped());
+                    self.observer.context_finished(&report);
+                    self.report.contexts.push(report);
+                }
+                allowed
+            })
+            .collect()
+    }
+
+    pub fn context_ran(
+        &mut self,
+        mut report: ContextReport,
+        run: Result<ContextRun, CharaError>,
+    ) -> Option<(ProcessorContext, ProcessorResult)> {
+        let ran = match run {
+            Ok(run) => {
+                report.outcome = Outcome::from_result(&run.result, run.duration);
+                run.result.ok().map(|result| (run.context, result))
+            }
+            Err(err) => {
+                report.outcome = Outcome::from_result::<()>(&Err(err), Duration::ZERO);
+                None
+            }
+        };
+        if let Some(err) = report.outcome.error.as_ref() {
+            error!(
+                "enrich {} on metadata {} {err}",
+                report.processor, report.metadata
+            );
+        }
+        if report.outcome.status == Status::Failed {
+            self.observer.context_failed(&report);
+        } else {
+            self.observer.context_finished(&report);
+        }
+        self.report.contexts.push(report);
+        ran
+    }
+
+    pub fn merge_result(
+        &mut self,
+        source_definition: &Readonly<Definition>,
+        context: ProcessorContext,
+        result: ProcessorResult,
+        path: &[DefinitionIdentity],
+    ) -> Result<Option<Child>, CharaError> {
+        let child = self.merge_context_result(source_definition, &context, result, path)?;
+        if let Some(identity) = path.last() {
+            self.observer
+                .result_merged(&identity.id, &context.definition);
+        }
+        Ok(child)
+    }
+
+    fn merge_context_result(
+        &mut self,
+        source_definition: &Readonly<Definition>,
+        context: &ProcessorContext,
+        result: ProcessorResult,
+        path: &[DefinitionIdentity],
+    ) -> Result<Option<Child>, CharaError> {
+        let mut metadata = context
+            .metadata
+            .write()
+            .map_err(|_| CharaError::Thread(ThreadError::Poison))?;
+        if let Some(enrichment) = result.enrichment {
+            if let (true, Some(mut edge_enrichment), Some(edge_context)) = (
+                context.definition.write.edge,
+                enrichment.edge,
+                &context.definition.edge,
+            ) {
+                if let Some(edge) = metadata.edges.get_mut(&edge_context.name) {
+                    edge.other.append(&mut edge_enrichment);
+                }
+            }
+            if let (true, Some(mut metadata_enrichment)) =
+                (context.definition.write.metadata, enrichment.metadata)
+            {
+                metadata.other.append(&mut metadata_enrichment);
+            }
+        }
+        let (Some(mut result_definition), Some(edge_context)) =
+            (result.definition, context.definition.edge.as_ref())
+        else {
+            return Ok(None);
+        };
+        let Some(edge) = metadata.edges.get_mut(&edge_context.name) else {
+            return Ok(None);
+        };
+        let identity = DefinitionIdentity::from(&result_definition);
+        if let Some(visited) = path.iter().find(|visited| visited.matches(&identity)) {
+            warn!(
+                "Cycle detected on metadata {} edge {}, {} was already visited",
+                context.definition.metadata.name, edge_context.name, visited.id
+            );
+            edge.definition = Some(Definition::reference(visited));
+            self.report.cycles.push(Cycle {
+                metadata: context.definition.metadata.name.clone(),
+                edge: edge_context.name.clone(),
+                path: path.to_vec(),
+                definition: visited.clone(),
+            });
+            return Ok(None);
+        }
+        if let Some(src_edge) = edge.edge.value() {
+            if let Some(foreign_definition) = src_edge.definition.as_ref() {
+                if let Ok(foreign_definition) = foreign_definition.read() {
+                    if let Some(foreign_definition) = foreign_definition.output.as_ref() {
+                        result_definition.merge(foreign_definition);
+                    }
+                }
+            }
+        }
+        edge.definition.merge(&Some(result_definition));
+        if !self.options.allows_depth(path.len()) {
+            info!(
+                "Maximum depth reached, skip {} definition processing",
+                identity.id
+            );
+            return Ok(None);
+        }
+        if !self.options.allows_nodes(self.nodes + 1) {
+            info!(
+                "Maximum nodes reached, skip {} definition processing",
+                identity.id
+            );
+            return Ok(None);
+        }
+        Ok(edge.definition.as_mut().map(|definition| {
+            self.nodes += 1;
+            definition.parent = Some(source_definition.clone());
+            Child {
+                metadata: context.metadata.clone(),
+                edge: edge_context.name.clone(),
+                definition: definition.clone(),
+                path: [path, &[identity]].concat(),
+            }
+        }))
+    }
+}
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use common::thread::Readonly;
+use engine::{
+    asynchronous::AsyncDefinitions,
+    contexts::ProcessorContext,
+    definition::{definition::Definition, input::DefinedDefinitionInput},
+    errors::CharaError,
+    processor::ProcessorResult,
+    report::RunReport,
+    Definitions,
+};
+
+type Enrich = dyn Fn(&ProcessorContext) -> Result<ProcessorResult, CharaError> + Send + Sync;
+
+pub struct FakeDefinitions {
+    enrich: Box<Enrich>,
+}
+
+impl FakeDefinitions {
+    pub fn new(
+        enrich: impl Fn(&ProcessorContext) -> Result<ProcessorResult, CharaError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        Self {
+            enrich: Box::new(enrich),
+        }
+    }
+}
+
+impl Definitions for FakeDefinitions {
+    fn get(&self, _definition: &DefinedDefinitionInput) -> Result<Definition, CharaError> {
+        Err(CharaError::Process("No foreign definition".to_string()))
+    }
+
+    fn enrich(
+        &self,
+        context: &ProcessorContext,
+        _parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        (self.enrich)(context)
+    }
+
+    fn save(&self, _definition: &Definition) -> Result<(), CharaError> {
+        Ok(())
+    }
+
+    fn save_report(&self, _definition: &Definition, _report: &RunReport) -> Result<(), CharaError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AsyncDefinitions for FakeDefinitions {
+    async fn get(&self, definition: &DefinedDefinitionInput) -> Result<Definition, CharaError> {
+        Definitions::get(self, definition)
+    }
+
+    async fn enrich(
+        &self,
+        context: &ProcessorContext,
+        parent: Readonly<Definition>,
+    ) -> Result<ProcessorResult, CharaError> {
+        Definitions::enrich(self, context, parent)
+    }
+
+    async fn save(&self, definition: &Definition) -> Result<(), CharaError> {
+        Definitions::save(self, definition)
+    }
+
+    async fn save_report(
+        &self,
+        definition: &Definition,
+        report: &RunReport,
+    ) -> Result<(), CharaError> {
+        Definitions::save_report(self, definition, report)
+    }
+}
@@ -1,4 +1,5 @@
 pub mod definition;
+pub mod definitions;
 pub mod metadata;
 pub mod edge_override;
 pub mod edge;
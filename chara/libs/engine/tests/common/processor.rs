@@ -3,7 +3,7 @@ use std::vec;
 use engine::{
     cli::{DraftArguments, DraftEnvironments},
     definition::install::Install,
-    processor::Processor,
+    processor::{Processor, Protocol},
 };
 
 pub struct ProcessorBuilder {
@@ -50,5 +50,11 @@ fn empty_processor() -> Processor {
         environments: vec![],
         install: None,
         program: "".to_string(),
+        timeout: None,
+        retry: None,
+        protocol: Protocol::Arguments,
+        manifest: None,
+        location: None,
+        network: false,
     }
 }
@@ -0,0 +1,100 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use engine::{
+    executor::{Executor, Task},
+    processor::processor_identity,
+};
+
+fn track(running: &Arc<AtomicUsize>, max_running: &Arc<AtomicUsize>) -> impl FnOnce() + Send {
+    let running = running.clone();
+    let max_running = max_running.clone();
+    move || {
+        let current = running.fetch_add(1, Ordering::SeqCst) + 1;
+        max_running.fetch_max(current, Ordering::SeqCst);
+        thread::sleep(Duration::from_millis(20));
+        running.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+pub fn should_bound_global_parallelism() {
+    let executor = Executor::new(Some(2), None);
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let tasks: Vec<Task<()>> = (0..8)
+        .map(|_| executor.spawn(track(&running, &max_running)))
+        .collect();
+    tasks.into_iter().for_each(|task| task.join().unwrap());
+
+    assert_eq!(2, max_running.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn should_bound_processor_parallelism() {
+    let executor = Executor::new(Some(4), Some(1));
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let tasks: Vec<Task<()>> = (0..4)
+        .map(|_| executor.spawn_processor("#/github", track(&running, &max_running)))
+        .collect();
+    tasks.into_iter().for_each(|task| task.join().unwrap());
+
+    assert_eq!(1, max_running.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn should_not_starve_other_processors() {
+    let executor = Executor::new(Some(4), Some(1));
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let github: Vec<Task<()>> = (0..4)
+        .map(|_| executor.spawn_processor("#/github", track(&running, &max_running)))
+        .collect();
+    let gitlab = executor.spawn_processor("#/gitlab", Instant::now);
+    let started = Instant::now();
+    let gitlab_started = gitlab.join().unwrap();
+    github.into_iter().for_each(|task| task.join().unwrap());
+
+    assert!(gitlab_started.duration_since(started) < Duration::from_millis(20));
+    assert_eq!(1, max_running.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn should_queue_processors_declared_at_distinct_locations_apart() {
+    let executor = Executor::new(Some(4), Some(1));
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+
+    let tasks: Vec<Task<()>> = ["/github/chara.json", "/gitlab/chara.json"]
+        .into_iter()
+        .map(|location| {
+            executor.spawn_processor(
+                &processor_identity("#/scrapper", Some(location)),
+                track(&running, &max_running),
+            )
+        })
+        .collect();
+    tasks.into_iter().for_each(|task| task.join().unwrap());
+
+    assert_eq!(2, max_running.load(Ordering::SeqCst));
+}
+
+#[test]
+pub fn should_fail_join_on_panicking_job() {
+    let executor = Executor::new(Some(1), None);
+
+    let task = executor.spawn(|| -> usize { panic!("job failure") });
+
+    assert!(task.join().is_err());
+    assert_eq!(2, executor.spawn(|| 2).join().unwrap());
+}
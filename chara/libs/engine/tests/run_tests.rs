@@ -0,0 +1,384 @@
+use std::sync::{Arc, Mutex};
+
+use ::common::thread::readonly;
+use common::{
+    definition::DefinitionBuilder, definitions::FakeDefinitions, edge::EdgeBuilder,
+    edge_override::EdgeOverrideBuilder, metadata::MetadataBuilder, processor::ProcessorBuilder,
+};
+use engine::{
+    asynchronous,
+    contexts::{DefinitionContextDto, ProcessorContext},
+    definition::definition::Definition,
+    errors::CharaError,
+    observer::{EngineObserver, NoopObserver},
+    options::RunOptions,
+    processor::{DraftProcessorOverride, ProcessorResult},
+    reference_value::LazyRef,
+    report::{ContextReport, Status},
+    run,
+};
+use serde_json::{Map, Value};
+use tokio_util::sync::CancellationToken;
+
+mod common;
+
+fn linked_definition(id: &str, next: Option<&str>) -> Definition {
+    let mut builder = DefinitionBuilder::new();
+    builder.with_id(id).with_name(id);
+    if let Some(next) = next {
+        let processor = readonly(ProcessorBuilder::new().with_program("scrapper").build());
+        let edge = readonly(
+            EdgeBuilder::new()
+                .with_processor(DraftProcessorOverride::processor(&Some(
+                    LazyRef::new_referenced_value("#/scrapper".to_string(), processor.clone()),
+                )))
+                .build(),
+        );
+        let metadata = MetadataBuilder::new()
+            .with_edge((
+                "#/workflows",
+                EdgeOverrideBuilder::new()
+                    .with_referenced_edge("#/workflows", edge.clone())
+                    .build(),
+            ))
+            .with_other(Map::from_iter([(
+                "next".to_string(),
+                Value::String(next.to_string()),
+            )]))
+            .build();
+        builder
+            .with_metadata_linked_to_edge(("build", readonly(metadata)), ("workflows", edge))
+            .with_processor(("scrapper", processor));
+    }
+    builder.build()
+}
+
+fn next_definition(next: &str, chain: &'static [&'static str]) -> Definition {
+    let position = chain.iter().position(|id| *id == next);
+    linked_definition(
+        next,
+        position
+            .and_then(|position| chain.get(position + 1))
+            .copied(),
+    )
+}
+
+fn followed_definition(context: &ProcessorContext, chain: &'static [&'static str]) -> Definition {
+    let next = context
+        .definition
+        .metadata
+        .value
+        .get("next")
+        .and_then(|next| next.as_str())
+        .unwrap_or_default()
+        .to_string();
+    next_definition(&next, chain)
+}
+
+fn follow(chain: &'static [&'static str]) -> FakeDefinitions {
+    FakeDefinitions::new(move |context| {
+        Ok(ProcessorResult {
+            enrichment: None,
+            definition: Some(followed_definition(context, chain)),
+        })
+    })
+}
+
+const LOCATION: &str = "/repository/chara.json";
+
+/// Returned definitions get fresh ids and the location of their parent, as definitions processors do.
+fn follow_located(chain: &'static [&'static str]) -> FakeDefinitions {
+    FakeDefinitions::new(move |context| {
+        let mut definition = followed_definition(context, chain);
+        definition.id = format!("{}-returned", definition.id);
+        definition.location = Some(LOCATION.to_string());
+        Ok(ProcessorResult {
+            enrichment: None,
+            definition: Some(definition),
+        })
+    })
+}
+
+fn located_definition(id: &str, next: Option<&str>) -> Definition {
+    let mut definition = linked_definition(id, next);
+    definition.location = Some(LOCATION.to_string());
+    definition
+}
+
+fn chain_length(definition: &Definition) -> usize {
+    1 + definition
+        .metadata
+        .get("build")
+        .and_then(|metadata| {
+            metadata
+                .read()
+                .ok()
+                .and_then(|metadata| metadata.edges["#/workflows"].definition.clone())
+        })
+        .map_or(0, |definition| chain_length(&definition))
+}
+
+#[test]
+pub fn should_stop_on_back_edge() {
+    let definition = linked_definition("a", Some("b"));
+
+    let (definition, report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "a"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(1, report.cycles.len());
+    let cycle = &report.cycles[0];
+    assert_eq!("a", cycle.definition.id);
+    assert_eq!(
+        vec!["a", "b"],
+        cycle
+            .path
+            .iter()
+            .map(|identity| identity.id.as_str())
+            .collect::<Vec<_>>()
+    );
+    let child = definition.metadata["build"].read().unwrap().edges["#/workflows"]
+        .definition
+        .clone()
+        .unwrap();
+    let reference = child.metadata["build"].read().unwrap().edges["#/workflows"]
+        .definition
+        .clone()
+        .unwrap();
+    assert_eq!("a", reference.id);
+    assert!(reference.metadata.is_empty());
+}
+
+#[test]
+pub fn should_not_report_cycle_on_acyclic_chain() {
+    let definition = linked_definition("a", Some("b"));
+
+    let (_definition, report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "c"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert!(report.cycles.is_empty());
+}
+
+#[test]
+pub fn should_enrich_children_located_at_their_parent() {
+    let (definition, report) = run(
+        located_definition("a", Some("b")),
+        Arc::new(follow_located(&["a", "b", "c"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert!(report.cycles.is_empty());
+    assert_eq!(3, chain_length(&definition));
+}
+
+#[test]
+pub fn should_stop_on_back_edge_to_same_location() {
+    let (_definition, report) = run(
+        located_definition("a", Some("b")),
+        Arc::new(follow_located(&["a", "b", "a"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(1, report.cycles.len());
+    assert_eq!("a", report.cycles[0].definition.id);
+}
+
+#[tokio::test]
+pub async fn should_stop_on_back_edge_asynchronously() {
+    let definition = linked_definition("a", Some("b"));
+
+    let (definition, report) = asynchronous::run(
+        definition,
+        Arc::new(follow(&["a", "b", "c", "a"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(1, report.cycles.len());
+    assert_eq!(4, chain_length(&definition));
+}
+
+#[test]
+pub fn should_not_process_definitions_below_max_depth() {
+    let definition = linked_definition("a", Some("b"));
+    let options = RunOptions {
+        max_depth: Some(1),
+        ..Default::default()
+    };
+
+    let (definition, _report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "c", "d", "e"])),
+        &options,
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(3, chain_length(&definition));
+}
+
+#[test]
+pub fn should_not_process_more_than_max_nodes() {
+    let definition = linked_definition("a", Some("b"));
+    let options = RunOptions {
+        max_nodes: Some(2),
+        ..Default::default()
+    };
+
+    let (definition, _report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "c", "d", "e"])),
+        &options,
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(3, chain_length(&definition));
+}
+
+#[test]
+pub fn should_only_run_allowed_processors() {
+    let definition = linked_definition("a", Some("b"));
+    let options = RunOptions {
+        processors: Some(vec!["#/other".to_string()]),
+        ..Default::default()
+    };
+
+    let (definition, _report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "c"])),
+        &options,
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(1, chain_length(&definition));
+}
+
+#[test]
+pub fn should_report_failed_processors() {
+    let definition = linked_definition("a", Some("b"));
+    let definitions = FakeDefinitions::new(|_context| {
+        Err(CharaError::ProcessExit(Some(1), "rate limited".to_string()))
+    });
+
+    let (_definition, report) = run(
+        definition,
+        Arc::new(definitions),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(1, report.failures());
+    let context = &report.contexts[0];
+    assert_eq!(Status::Failed, context.outcome.status);
+    assert_eq!("build", context.metadata);
+    assert_eq!(Some("#/workflows".to_string()), context.edge);
+    assert_eq!("#/scrapper", context.processor);
+    assert_eq!(Some("rate limited".to_string()), context.outcome.stderr);
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<String>>,
+}
+
+impl RecordingObserver {
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+impl EngineObserver for RecordingObserver {
+    fn definition_loaded(&self, definition: &Definition) {
+        self.record(format!("loaded {}", definition.id));
+    }
+    fn context_scheduled(&self, definition: &str, _context: &DefinitionContextDto) {
+        self.record(format!("scheduled {definition}"));
+    }
+    fn context_started(&self, definition: &str, _context: &DefinitionContextDto) {
+        self.record(format!("started {definition}"));
+    }
+    fn context_finished(&self, report: &ContextReport) {
+        self.record(format!("finished {}", report.definition));
+    }
+    fn result_merged(&self, definition: &str, _context: &DefinitionContextDto) {
+        self.record(format!("merged {definition}"));
+    }
+    fn definition_saved(&self, definition: &Definition) {
+        self.record(format!("saved {}", definition.id));
+    }
+}
+
+#[test]
+pub fn should_notify_observer_of_run_lifecycle() {
+    let definition = linked_definition("a", Some("b"));
+    let observer = Arc::new(RecordingObserver::default());
+
+    run(
+        definition,
+        Arc::new(follow(&["a", "b"])),
+        &RunOptions::default(),
+        observer.clone(),
+        CancellationToken::new(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        vec![
+            "loaded a",
+            "scheduled a",
+            "started a",
+            "finished a",
+            "merged a",
+            "loaded b",
+            "saved a"
+        ],
+        *observer.events.lock().unwrap()
+    );
+}
+
+#[test]
+pub fn should_report_contexts_as_cancelled() {
+    let definition = linked_definition("a", Some("b"));
+    let cancellation = CancellationToken::new();
+    cancellation.cancel();
+
+    let (definition, report) = run(
+        definition,
+        Arc::new(follow(&["a", "b", "c"])),
+        &RunOptions::default(),
+        Arc::new(NoopObserver),
+        cancellation,
+    )
+    .unwrap();
+
+    assert!(report.cancelled);
+    assert_eq!(Status::Cancelled, report.contexts[0].outcome.status);
+    assert_eq!(1, chain_length(&definition));
+}
@@ -0,0 +1,36 @@
+//! Wasm processor enriching the edge with the content of the metadata `file`.
+//! Build it with `cargo build -p file --target wasm32-wasip1`, the runtime only opens the definition directory.
+use std::{
+    fs,
+    io::{self, Read},
+    process::ExitCode,
+};
+
+use serde_json::{json, Value};
+
+fn enrich(request: &str) -> Result<Value, String> {
+    let request: Value = serde_json::from_str(request).map_err(|err| err.to_string())?;
+    let file = request["context"]["metadata"]["value"]["file"]
+        .as_str()
+        .ok_or("Metadata has no file")?;
+    let content = fs::read_to_string(file).map_err(|err| format!("{file} {err}"))?;
+    Ok(json!({ "enrichment": { "edge": { "file": file, "content": content } } }))
+}
+
+fn main() -> ExitCode {
+    let mut request = String::new();
+    if let Err(err) = io::stdin().read_to_string(&mut request) {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    match enrich(&request) {
+        Ok(result) => {
+            println!("{result}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
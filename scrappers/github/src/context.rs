@@ -62,6 +62,19 @@ impl DefinitionContext {
             write: WritePermission::from(value.write),
         })
     }
+    pub fn workflow_identity(&self) -> Option<String> {
+        if let Some(repository) = &self.metadata.repository {
+            return Some(format!(
+                "{}/{}/{}",
+                repository.owner, repository.name, self.metadata.file
+            ));
+        }
+        self.location.as_ref().and_then(|location| {
+            Path::new(location)
+                .parent()
+                .map(|parent| parent.join(&self.metadata.file).to_string_lossy().to_string())
+        })
+    }
     pub async fn workflow_content(&self) -> Result<String, Error> {
         if let Some(repository) = &self.metadata.repository {
             info!("Get workflow content for {repository}");
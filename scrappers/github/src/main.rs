@@ -2,11 +2,15 @@ use std::fs;
 
 use clap::{command, Parser};
 use context::DefinitionContext;
-use definitions::dto::definition::DefinitionContextDto;
+use definitions::dto::{
+    definition::{DefinitionContextDto, ProtocolDto},
+    manifest::{ProcessorManifestDto, WriteTargetDto},
+};
 use dtos::WorkflowDto;
 use errors::Error;
 use github::GithubContext;
 use log::{error, info};
+use serde_json::json;
 mod context;
 mod dtos;
 mod errors;
@@ -15,10 +19,13 @@ mod workflow;
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    #[arg(short, long)]
-    context: String,
-    #[arg(short, long)]
-    output: String,
+    #[arg(short, long, required_unless_present = "manifest")]
+    context: Option<String>,
+    #[arg(short, long, required_unless_present = "manifest")]
+    output: Option<String>,
+    /// Prints the processor manifest and exits.
+    #[arg(long)]
+    manifest: bool,
     #[arg(short, long)]
     server: Option<String>,
     #[arg(short, long)]
@@ -35,7 +42,12 @@ struct Args {
 async fn main() -> Result<(), Error> {
     colog::init();
     let args = Args::parse();
-    let res = match serde_json::from_str::<DefinitionContextDto>(&args.context)
+    if args.manifest {
+        println!("{}", serde_json::to_string(&manifest()).map_err(Error::Json)?);
+        return Ok(());
+    }
+    let output = args.output.unwrap_or_default();
+    let res = match serde_json::from_str::<DefinitionContextDto>(&args.context.unwrap_or_default())
         .map_err(Error::Json)
         .and_then(|ctx: DefinitionContextDto| {
             DefinitionContext::new(
@@ -57,7 +69,7 @@ async fn main() -> Result<(), Error> {
                 .to_processor_result(context)
                 .and_then(|def| serde_json::to_string(&def).map_err(Error::Json))
             {
-                fs::write(args.output, &def).map_err(Error::IO)?;
+                fs::write(output, &def).map_err(Error::IO)?;
             }
             Ok(())
         }
@@ -68,3 +80,23 @@ async fn main() -> Result<(), Error> {
     }
     res
 }
+
+fn manifest() -> ProcessorManifestDto {
+    ProcessorManifestDto {
+        name: "github".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol: Some(ProtocolDto::Arguments),
+        metadata: Some(json!({
+            "type": "object",
+            "required": ["file"],
+            "properties": {
+                "file": { "type": "string" },
+                "owner": { "type": "string" },
+                "repository": { "type": "string" }
+            },
+            "dependentRequired": { "owner": ["repository"], "repository": ["owner"] }
+        })),
+        edge: Some(json!({ "type": "object" })),
+        writes: vec![WriteTargetDto::Definition],
+    }
+}
@@ -61,7 +61,7 @@ impl Workflow {
     pub fn to_definition(self, context: DefinitionContext) -> Result<DefinitionDto, Error> {
         dbg!(&context);
         Ok(DefinitionDto {
-            id: None,
+            id: context.workflow_identity(),
             name: self.name,
             metadata: self
                 .jobs